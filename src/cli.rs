@@ -0,0 +1,193 @@
+use std::path::Path;
+use std::{fs, io};
+
+use aoc2024::Position;
+
+use crate::problems::{day14, day15, day19, day20, day21, day9};
+
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .map(|i| args.get(i + 1).expect("Missing flag value").as_str())
+}
+
+fn read_data(day: &str, args: &[String]) -> String {
+    let path = flag(args, "--file")
+        .map(|path| path.to_string())
+        .unwrap_or(format!("data/{}.data", day));
+    fs::read_to_string(path).expect("Failed to read file")
+}
+
+fn run_day9(args: &[String]) {
+    let contents = read_data("day9", args);
+
+    for strategy in [day9::Strategy::Fragment, day9::Strategy::WholeFile] {
+        let disk = day9::compact(&contents, strategy);
+        let stats = disk.fragmentation();
+
+        println!("{:?}: checksum {}", strategy, disk.checksum());
+        println!(
+            "  free gaps: {}, largest gap: {}, files split: {}",
+            stats.free_gaps, stats.largest_gap, stats.files_split
+        );
+        println!("  disk map: {}", disk.serialise());
+        if args.iter().any(|arg| arg == "--render") {
+            println!("  {}", disk.render());
+        }
+    }
+}
+
+fn day14_room(contents: &str, args: &[String]) -> Position {
+    let room = day14::room_size(contents);
+    let size = |name: &str, default: i64| {
+        flag(args, name)
+            .map(|n| n.parse().expect("Invalid room size"))
+            .unwrap_or(default)
+    };
+
+    Position {
+        x: size("--width", room.x),
+        y: size("--height", room.y),
+    }
+}
+
+fn run_day14(args: &[String]) {
+    let contents = read_data("day14", args);
+    let room = day14_room(&contents, args);
+
+    println!("Room: {}x{}", room.x, room.y);
+    println!(
+        "Safety factor: {}",
+        day14::safety_factor(&contents, &room, 100)
+    );
+
+    match day14::period(&contents, &room) {
+        Some(period) => println!("Period: {} seconds", period),
+        None => println!("Period: too long to represent"),
+    }
+    match day14::collision_free(&contents, &room) {
+        Some(free) => match free.first() {
+            Some(second) => println!("First second without overlaps: {}", second),
+            None => println!("First second without overlaps: never"),
+        },
+        None => println!("First second without overlaps: period too long to check"),
+    }
+
+    let egg = day14::easter_egg(&contents, &room, day14::Score::Variance);
+    match egg {
+        Some(second) => println!("Easter egg: {} seconds", second),
+        None => println!("Easter egg: not found"),
+    }
+
+    if let Some(dir) = flag(args, "--export") {
+        let second = |name: &str, default: i64| {
+            flag(args, name)
+                .map(|n| n.parse().expect("Invalid second"))
+                .unwrap_or(default)
+        };
+        let from = second("--from", egg.unwrap_or(0));
+        let to = second("--to", from);
+        let scale: usize = flag(args, "--scale")
+            .map(|n| n.parse().expect("Invalid scale"))
+            .unwrap_or(1);
+        let format = match args.iter().any(|arg| arg == "--pgm") {
+            true => day14::Image::Pgm,
+            false => day14::Image::Pbm,
+        };
+
+        let frames =
+            day14::export_frames(&contents, &room, from..=to, format, scale, Path::new(dir))
+                .expect("Failed to export frames");
+        println!("Exported {} frames to {}", frames.len(), dir);
+    }
+}
+
+fn run_day15(args: &[String]) {
+    let contents = read_data("day15", args);
+    let width: usize = flag(args, "--width")
+        .map(|n| n.parse().expect("Invalid box width"))
+        .unwrap_or(1);
+
+    let (mut warehouse, _) = day15::warehouse(&contents, width);
+    day15::play(&mut warehouse, io::stdin().lock(), &mut io::stdout());
+}
+
+fn run_day19(args: &[String]) {
+    let contents = read_data("day19", args);
+    let limit: usize = flag(args, "--arrangements")
+        .map(|n| n.parse().expect("Invalid arrangement count"))
+        .unwrap_or(0);
+
+    let (towels, designs) = day19::towels(&contents);
+    println!(
+        "Colours: {}",
+        towels.alphabet().colours().iter().collect::<String>()
+    );
+    for design in designs.iter() {
+        let report = towels.report(design);
+        match report.fewest {
+            Some(fewest) => println!(
+                "{}: {} arrangements, at least {} towels",
+                report.design, report.arrangements, fewest
+            ),
+            None => println!("{}: impossible", report.design),
+        }
+
+        for arrangement in towels.first_arrangements(design, limit) {
+            println!("  {}", arrangement.join(" "));
+        }
+    }
+}
+
+fn run_day20(args: &[String]) {
+    let contents = read_data("day20", args);
+    let number = |name: &str, default: i64| {
+        flag(args, name)
+            .map(|n| n.parse().expect("Invalid number"))
+            .unwrap_or(default)
+    };
+    let threshold = number("--threshold", 100);
+    let cheat_length = number("--cheat-length", 20);
+
+    for (saving, count) in day20::savings_histogram(&contents, threshold, cheat_length) {
+        println!("{} cheats save {} picoseconds", count, saving);
+    }
+
+    if let Some(limit) = flag(args, "--list") {
+        let limit = limit.parse().expect("Invalid cheat count");
+        for cheat in day20::list_cheats(&contents, threshold, cheat_length, Some(limit)) {
+            println!(
+                "{},{} -> {},{}: saves {}",
+                cheat.start.x, cheat.start.y, cheat.end.x, cheat.end.y, cheat.saving
+            );
+        }
+    }
+}
+
+fn run_day21(args: &[String]) {
+    let contents = read_data("day21", args);
+    let robots: u64 = flag(args, "--robots")
+        .map(|n| n.parse().expect("Invalid robot count"))
+        .unwrap_or(25);
+
+    let codes = day21::complexities(&contents, robots);
+    for code in codes.iter() {
+        println!(
+            "{}: {} presses * {} = {}",
+            code.code, code.length, code.numeric, code.complexity
+        );
+    }
+    println!("Total: {}", day21::total_complexity(&contents, robots));
+}
+
+pub fn run(args: &[String]) {
+    match args[0].as_str() {
+        "day9" => run_day9(&args[1..]),
+        "day14" => run_day14(&args[1..]),
+        "day15" => run_day15(&args[1..]),
+        "day19" => run_day19(&args[1..]),
+        "day20" => run_day20(&args[1..]),
+        "day21" => run_day21(&args[1..]),
+        command => panic!("Unknown command {}", command),
+    }
+}
@@ -0,0 +1,322 @@
+use crate::Position;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+impl Connectivity {
+    pub fn offsets(&self) -> &'static [Position] {
+        static FOUR: [Position; 4] = [
+            Position { x: -1, y: 0 },
+            Position { x: 0, y: 1 },
+            Position { x: 1, y: 0 },
+            Position { x: 0, y: -1 },
+        ];
+        static EIGHT: [Position; 8] = [
+            Position { x: -1, y: 0 },
+            Position { x: -1, y: 1 },
+            Position { x: 0, y: 1 },
+            Position { x: 1, y: 1 },
+            Position { x: 1, y: 0 },
+            Position { x: 1, y: -1 },
+            Position { x: 0, y: -1 },
+            Position { x: -1, y: -1 },
+        ];
+
+        match self {
+            Connectivity::Four => &FOUR,
+            Connectivity::Eight => &EIGHT,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Components {
+    pub labels: Vec<Vec<usize>>,
+    pub cells: Vec<Vec<Position>>,
+}
+
+impl Components {
+    pub fn label_at(&self, position: &Position) -> Option<usize> {
+        if position.x < 0 || position.y < 0 {
+            return None;
+        }
+
+        self.labels
+            .get(position.x as usize)?
+            .get(position.y as usize)
+            .copied()
+    }
+}
+
+// flood fills the grid, two neighbouring cells end up in the same component
+// when `same` says so. Labels are numbered in the order components are found
+// scanning row by row
+pub fn label<T, F>(grid: &[Vec<T>], connectivity: Connectivity, same: F) -> Components
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let mut labels: Vec<Vec<usize>> = grid.iter().map(|row| vec![usize::MAX; row.len()]).collect();
+    let mut cells: Vec<Vec<Position>> = Vec::new();
+
+    for (x, row) in grid.iter().enumerate() {
+        for y in 0..row.len() {
+            if labels[x][y] != usize::MAX {
+                continue;
+            }
+
+            let id = cells.len();
+            let mut component = Vec::new();
+            let mut to_visit = vec![Position {
+                x: x as i64,
+                y: y as i64,
+            }];
+            labels[x][y] = id;
+
+            while let Some(position) = to_visit.pop() {
+                component.push(position);
+                let tile = &grid[position.x as usize][position.y as usize];
+
+                for offset in connectivity.offsets() {
+                    let next = position + *offset;
+                    if next.x < 0 || next.y < 0 {
+                        continue;
+                    }
+
+                    let (nx, ny) = (next.x as usize, next.y as usize);
+                    let Some(next_tile) = grid.get(nx).and_then(|row| row.get(ny)) else {
+                        continue;
+                    };
+
+                    if labels[nx][ny] == usize::MAX && same(tile, next_tile) {
+                        labels[nx][ny] = id;
+                        to_visit.push(next);
+                    }
+                }
+            }
+
+            cells.push(component);
+        }
+    }
+
+    Components { labels, cells }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    pub fn new(count: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..count).collect(),
+            size: vec![1; count],
+        }
+    }
+
+    pub fn find(&mut self, item: usize) -> usize {
+        let mut root = item;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut item = item;
+        while self.parent[item] != root {
+            let next = self.parent[item];
+            self.parent[item] = root;
+            item = next;
+        }
+
+        root
+    }
+
+    // returns false if the two were already joined
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        let (big, small) = match self.size[a] >= self.size[b] {
+            true => (a, b),
+            false => (b, a),
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+
+        true
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn size(&mut self, item: usize) -> usize {
+        let root = self.find(item);
+        self.size[root]
+    }
+}
+
+// grid components built up one cell at a time, each added cell is joined to
+// whichever of its neighbours are already present
+#[derive(Debug, Clone)]
+pub struct IncrementalLabeller {
+    height: usize,
+    width: usize,
+    connectivity: Connectivity,
+    present: Vec<bool>,
+    sets: UnionFind,
+    count: usize,
+}
+
+impl IncrementalLabeller {
+    pub fn new(height: usize, width: usize, connectivity: Connectivity) -> IncrementalLabeller {
+        IncrementalLabeller {
+            height,
+            width,
+            connectivity,
+            present: vec![false; height * width],
+            sets: UnionFind::new(height * width),
+            count: 0,
+        }
+    }
+
+    fn index(&self, position: &Position) -> Option<usize> {
+        let in_bounds = position.x >= 0
+            && position.y >= 0
+            && (position.x as usize) < self.height
+            && (position.y as usize) < self.width;
+
+        in_bounds.then(|| position.x as usize * self.width + position.y as usize)
+    }
+
+    pub fn add(&mut self, position: &Position) {
+        let index = self.index(position).expect("Position out of bounds");
+        if self.present[index] {
+            return;
+        }
+
+        self.present[index] = true;
+        self.count += 1;
+
+        for offset in self.connectivity.offsets() {
+            let Some(next) = self.index(&(*position + *offset)) else {
+                continue;
+            };
+
+            if self.present[next] && self.sets.union(index, next) {
+                self.count -= 1;
+            }
+        }
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        self.index(position)
+            .is_some_and(|index| self.present[index])
+    }
+
+    // a representative id for the component, stable until the next `add`
+    pub fn label(&mut self, position: &Position) -> Option<usize> {
+        let index = self.index(position)?;
+        match self.present[index] {
+            true => Some(self.sets.find(index)),
+            false => None,
+        }
+    }
+
+    pub fn connected(&mut self, a: &Position, b: &Position) -> bool {
+        match (self.label(a), self.label(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    pub fn component_size(&mut self, position: &Position) -> usize {
+        match self.label(position) {
+            Some(label) => self.sets.size(label),
+            None => 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_works() {
+        let grid = vec![vec![true, false], vec![false, true]];
+
+        let four = label(&grid, Connectivity::Four, |a, b| a == b);
+        assert_eq!(four.cells.len(), 4);
+        assert_ne!(
+            four.label_at(&Position { x: 0, y: 0 }),
+            four.label_at(&Position { x: 1, y: 1 })
+        );
+
+        let eight = label(&grid, Connectivity::Eight, |a, b| a == b);
+        assert_eq!(eight.cells.len(), 2);
+        assert_eq!(eight.labels, vec![vec![0, 1], vec![1, 0]]);
+        assert_eq!(eight.label_at(&Position { x: -1, y: 0 }), None);
+
+        // neighbours join when their heights differ by at most one
+        let heights = vec![vec![1, 2, 5], vec![6, 3, 5]];
+        let close = label(&heights, Connectivity::Four, |a: &i32, b: &i32| {
+            (a - b).abs() <= 1
+        });
+        assert_eq!(close.labels, vec![vec![0, 0, 1], vec![2, 0, 1]]);
+        assert_eq!(close.cells[0].len(), 3);
+    }
+
+    #[test]
+    fn union_find_works() {
+        let mut sets = UnionFind::new(5);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(1, 2));
+        assert!(!sets.union(2, 0));
+
+        assert!(sets.connected(0, 2));
+        assert!(!sets.connected(0, 3));
+        assert_eq!(sets.size(2), 3);
+        assert_eq!(sets.size(4), 1);
+    }
+
+    #[test]
+    fn incremental_labeller_works() {
+        let mut labeller = IncrementalLabeller::new(3, 3, Connectivity::Four);
+        let (a, b, c) = (
+            Position { x: 0, y: 0 },
+            Position { x: 0, y: 2 },
+            Position { x: 0, y: 1 },
+        );
+
+        labeller.add(&a);
+        labeller.add(&b);
+        assert_eq!(labeller.count(), 2);
+        assert!(!labeller.connected(&a, &b));
+        assert_eq!(labeller.component_size(&a), 1);
+
+        labeller.add(&c);
+        assert_eq!(labeller.count(), 1);
+        assert!(labeller.connected(&a, &b));
+        assert_eq!(labeller.component_size(&b), 3);
+
+        // adding the same cell twice changes nothing
+        labeller.add(&c);
+        assert_eq!(labeller.count(), 1);
+
+        let never = Position { x: 2, y: 2 };
+        assert!(!labeller.contains(&never));
+        assert!(!labeller.connected(&a, &never));
+        assert_eq!(labeller.component_size(&never), 0);
+        assert_eq!(labeller.label(&Position { x: 5, y: 0 }), None);
+    }
+}
@@ -3,6 +3,9 @@ use std::{
     slice
 };
 
+pub mod components;
+pub mod number_theory;
+
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone, PartialOrd, Ord)]
 pub struct Position {
     pub x: i64,
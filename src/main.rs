@@ -1,10 +1,17 @@
-use std::fs;
+use std::{env, fs};
 
 use problems::day24::{part1, part2};
 
+mod cli;
 pub mod problems;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        cli::run(&args);
+        return;
+    }
+
     let contents = fs::read_to_string("data/day24.data").expect("Failed to read file");
 
     println!("Part 1: {}", part1(&contents[..]));
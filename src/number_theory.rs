@@ -0,0 +1,188 @@
+// the i128 versions do the work, the i64 ones come back as None if the answer
+// doesn't fit. Anything with a modulus wants it to be positive
+
+// (g, x, y) with a * x + b * y = g and g >= 0, None if any of them overflow
+pub fn extended_gcd_i128(a: i128, b: i128) -> Option<(i128, i128, i128)> {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x): (i128, i128) = (1, 0);
+    let (mut old_y, mut y): (i128, i128) = (0, 1);
+
+    while r != 0 {
+        let quotient = old_r.checked_div(r)?;
+        (old_r, r) = (r, old_r.checked_sub(quotient.checked_mul(r)?)?);
+        (old_x, x) = (x, old_x.checked_sub(quotient.checked_mul(x)?)?);
+        (old_y, y) = (y, old_y.checked_sub(quotient.checked_mul(y)?)?);
+    }
+
+    match old_r < 0 {
+        true => Some((
+            old_r.checked_neg()?,
+            old_x.checked_neg()?,
+            old_y.checked_neg()?,
+        )),
+        false => Some((old_r, old_x, old_y)),
+    }
+}
+
+pub fn extended_gcd(a: i64, b: i64) -> Option<(i64, i64, i64)> {
+    let (g, x, y) = extended_gcd_i128(a as i128, b as i128)?;
+    Some((g.try_into().ok()?, x.try_into().ok()?, y.try_into().ok()?))
+}
+
+pub fn checked_lcm_i128(a: i128, b: i128) -> Option<i128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    let (g, _, _) = extended_gcd_i128(a, b)?;
+    (a / g).checked_mul(b)?.checked_abs()
+}
+
+pub fn checked_lcm(a: i64, b: i64) -> Option<i64> {
+    checked_lcm_i128(a as i128, b as i128).and_then(|lcm| lcm.try_into().ok())
+}
+
+pub fn mod_inverse_i128(a: i128, modulus: i128) -> Option<i128> {
+    if modulus <= 0 {
+        return None;
+    }
+
+    let (g, x, _) = extended_gcd_i128(a.rem_euclid(modulus), modulus)?;
+    (g == 1).then(|| x.rem_euclid(modulus))
+}
+
+pub fn mod_inverse(a: i64, modulus: i64) -> Option<i64> {
+    mod_inverse_i128(a as i128, modulus as i128).map(|inverse| inverse as i64)
+}
+
+pub fn mod_pow_i128(base: i128, exponent: u128, modulus: i128) -> Option<i128> {
+    if modulus <= 0 {
+        return None;
+    }
+
+    let mut result: i128 = 1 % modulus;
+    let mut base = base.rem_euclid(modulus);
+    let mut exponent = exponent;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.checked_mul(base)? % modulus;
+        }
+        base = base.checked_mul(base)? % modulus;
+        exponent >>= 1;
+    }
+
+    Some(result)
+}
+
+pub fn mod_pow(base: i64, exponent: u64, modulus: i64) -> Option<i64> {
+    mod_pow_i128(base as i128, exponent as u128, modulus as i128).map(|result| result as i64)
+}
+
+// solves a * x = b (mod modulus), every solution is x plus a multiple of the
+// step so it comes back as (x, step) with 0 <= x < step
+pub fn linear_congruence_i128(a: i128, b: i128, modulus: i128) -> Option<(i128, i128)> {
+    if modulus <= 0 {
+        return None;
+    }
+
+    let (g, _, _) = extended_gcd_i128(a.rem_euclid(modulus), modulus)?;
+    if b.rem_euclid(modulus) % g != 0 {
+        return None;
+    }
+
+    let step = modulus / g;
+    let inverse = mod_inverse_i128(a.rem_euclid(modulus) / g, step)?;
+    let x = (b.rem_euclid(modulus) / g).checked_mul(inverse)? % step;
+
+    Some((x, step))
+}
+
+pub fn linear_congruence(a: i64, b: i64, modulus: i64) -> Option<(i64, i64)> {
+    linear_congruence_i128(a as i128, b as i128, modulus as i128)
+        .map(|(x, step)| (x as i64, step as i64))
+}
+
+// solves x = residue (mod modulus) for every pair, the moduli don't have to
+// be coprime. Gives back (x, lcm of the moduli) with 0 <= x < lcm
+pub fn crt_i128(congruences: &[(i128, i128)]) -> Option<(i128, i128)> {
+    let mut result: (i128, i128) = (0, 1);
+
+    for &(residue, modulus) in congruences {
+        if modulus <= 0 {
+            return None;
+        }
+
+        let (r1, m1) = result;
+        let r2 = residue.rem_euclid(modulus);
+        let (g, _, _) = extended_gcd_i128(m1, modulus)?;
+
+        if (r2 - r1) % g != 0 {
+            return None;
+        }
+
+        let lcm = checked_lcm_i128(m1, modulus)?;
+        let step = modulus / g;
+        let inverse = mod_inverse_i128(m1 / g, step)?;
+        let t = ((r2 - r1) / g).rem_euclid(step).checked_mul(inverse)? % step;
+        let x = m1.checked_mul(t)?.checked_add(r1)?.rem_euclid(lcm);
+
+        result = (x, lcm);
+    }
+
+    Some(result)
+}
+
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    let congruences: Vec<(i128, i128)> = congruences
+        .iter()
+        .map(|&(residue, modulus)| (residue as i128, modulus as i128))
+        .collect();
+
+    let (x, lcm) = crt_i128(&congruences)?;
+    Some((x.try_into().ok()?, lcm.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_gcd_works() {
+        assert_eq!(extended_gcd(240, 46), Some((2, -9, 47)));
+        assert_eq!(extended_gcd(-4, 6), Some((2, 1, 1)));
+        // 2^63 doesn't fit back into an i64
+        assert_eq!(extended_gcd(i64::MIN, 0), None);
+
+        assert_eq!(extended_gcd_i128(i128::MAX, 1), Some((1, 0, 1)));
+        assert_eq!(extended_gcd_i128(-6, -4), Some((2, -1, 1)));
+        assert_eq!(extended_gcd_i128(i128::MIN, 0), None);
+        assert_eq!(extended_gcd_i128(i128::MIN, -1), None);
+    }
+
+    #[test]
+    fn crt_works() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(2, 6), (8, 10)]), Some((8, 30)));
+        assert_eq!(crt(&[(1, 6), (2, 10)]), None);
+        assert_eq!(crt(&[(0, 0)]), None);
+        assert_eq!(crt(&[(1, i64::MAX), (0, i64::MAX - 1)]), None);
+    }
+
+    #[test]
+    fn modular_arithmetic_works() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(4, 10), None);
+        assert_eq!(mod_pow(3, 200, 13), Some(9));
+        assert_eq!(checked_lcm(i64::MAX, i64::MAX - 1), None);
+        assert_eq!(checked_lcm_i128(-4, 6), Some(12));
+        assert_eq!(checked_lcm_i128(i128::MIN, 1), None);
+        assert_eq!(checked_lcm_i128(i128::MIN, 2), None);
+        assert_eq!(mod_inverse_i128(i128::MIN, 7), Some(3));
+
+        // 6x = 4 (mod 10) has x = 4 and every 5 after it
+        assert_eq!(linear_congruence(6, 4, 10), Some((4, 5)));
+        assert_eq!(linear_congruence(6, 3, 10), None);
+        assert_eq!(linear_congruence(0, 0, 7), Some((0, 1)));
+    }
+}
@@ -1,81 +1,156 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 
-fn parse(data: &str) -> HashMap<i64, i64> {
-    data.trim()
-        .split(" ")
-        .map(|v| (v.parse().unwrap(), 1))
-        .collect()
+// stones are u128 so any u64 in the input can be multiplied by 2024. A stone
+// with an odd number of digits grows by at most 4 digits and the next
+// multiplication leaves an even number to split, so none get near u128::MAX
+type Rule = Box<dyn Fn(u128) -> Option<Vec<u128>>>;
+
+fn parse(data: &str) -> HashMap<u128, u128> {
+    let mut stones = HashMap::new();
+    for stone in data.split_whitespace() {
+        *stones.entry(stone.parse().unwrap()).or_insert(0) += 1;
+    }
+
+    stones
+}
+
+// rewrites each stone with the first rule that matches it, counts are kept
+// per distinct stone value so the order of the line never matters
+#[derive(Default)]
+pub struct RuleEngine {
+    rules: Vec<Rule>,
+    memo: RefCell<HashMap<(u128, u64), u128>>,
 }
 
-fn blink(stones: &mut HashMap<i64, i64>) {
-    // we can't borrow stones immutably and also borrow mutate,
-    // so we'll collect the hits, then apply to stones counts.
-    let mut hits: HashMap<i64, i64> = HashMap::new();
-
-    for (&stone, &stone_count) in stones.iter() {
-        // First, decrement the stone
-        hits.insert(stone, *hits.get(&stone).unwrap_or(&0) - stone_count);
-
-        if stone == 0 {
-            // case 1:
-            hits.insert(1, *hits.get(&1).unwrap_or(&0) + stone_count);
-        } else {
-            let number_of_digits = (stone).ilog10() + 1;
-
-            if number_of_digits % 2 == 0 {
-                // case 2:
-                let top_half = stone / (10 as i64).pow(number_of_digits / 2);
-                hits.insert(top_half, *hits.get(&top_half).unwrap_or(&0) + stone_count);
-
-                let bottom_half = stone - (top_half * (10 as i64).pow(number_of_digits / 2));
-                hits.insert(
-                    bottom_half,
-                    *hits.get(&bottom_half).unwrap_or(&0) + stone_count,
-                );
-            } else {
-                // case 3:
-                let new_value = stone * 2024;
-                hits.insert(new_value, *hits.get(&new_value).unwrap_or(&0) + stone_count);
+impl RuleEngine {
+    pub fn new() -> RuleEngine {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, rule: impl Fn(u128) -> Option<Vec<u128>> + 'static) -> RuleEngine {
+        self.rules.push(Box::new(rule));
+        self.memo.borrow_mut().clear();
+        self
+    }
+
+    // the three rules from the puzzle
+    pub fn standard() -> RuleEngine {
+        RuleEngine::new()
+            .with_rule(|stone| (stone == 0).then(|| vec![1]))
+            .with_rule(|stone| {
+                let number_of_digits = stone.checked_ilog10()? + 1;
+                if number_of_digits % 2 != 0 {
+                    return None;
+                }
+
+                let half = 10u128.pow(number_of_digits / 2);
+                Some(vec![stone / half, stone % half])
+            })
+            .with_rule(|stone| {
+                let stone = stone
+                    .checked_mul(2024)
+                    .unwrap_or_else(|| panic!("Stone {stone} overflows when multiplied by 2024"));
+                Some(vec![stone])
+            })
+    }
+
+    fn rewrite(&self, stone: u128) -> Vec<u128> {
+        self.rules
+            .iter()
+            .find_map(|rule| rule(stone))
+            .unwrap_or_else(|| panic!("No rule matches stone {stone}"))
+    }
+
+    pub fn blink(&self, stones: &HashMap<u128, u128>) -> HashMap<u128, u128> {
+        let mut result: HashMap<u128, u128> = HashMap::new();
+
+        for (&stone, &count) in stones.iter() {
+            for new_stone in self.rewrite(stone) {
+                let entry = result.entry(new_stone).or_insert(0);
+                *entry = entry.saturating_add(count);
             }
         }
+
+        result
     }
 
-    for (stone, count) in &hits {
-        let current_value = *stones.get(stone).unwrap_or(&0);
-        if current_value + count == 0 {
-            stones.remove(stone);
-        } else {
-            stones.insert(*stone, current_value + count);
+    // how many stones a single stone turns into after `blinks` blinks, counts
+    // saturate at u128::MAX. Each stone it splits into is counted the same
+    // way with one blink fewer, so every (stone, blinks) is only ever worked
+    // out once across all queries. This recurses once per blink
+    pub fn count(&self, stone: u128, blinks: u64) -> u128 {
+        if blinks == 0 {
+            return 1;
         }
+        if let Some(&count) = self.memo.borrow().get(&(stone, blinks)) {
+            return count;
+        }
+
+        let count = self.rewrite(stone).into_iter().fold(0u128, |acc, next| {
+            acc.saturating_add(self.count(next, blinks - 1))
+        });
+        self.memo.borrow_mut().insert((stone, blinks), count);
+
+        count
     }
-}
 
-pub fn part1(data: &str) -> i64 {
-    let mut stones = parse(data);
+    // stones already in the memo are looked up, the rest blink together so a
+    // value shared between them is only rewritten once per generation
+    pub fn count_all(&self, stones: &HashMap<u128, u128>, blinks: u64) -> u128 {
+        let mut total = 0u128;
+        let mut remaining: HashMap<u128, u128> = HashMap::new();
+
+        for (&stone, &n) in stones.iter() {
+            match self.memo.borrow().get(&(stone, blinks)) {
+                Some(&count) => total = total.saturating_add(count.saturating_mul(n)),
+                None => {
+                    remaining.insert(stone, n);
+                }
+            }
+        }
 
-    let number_of_blinks = 25;
+        for _ in 0..blinks {
+            remaining = self.blink(&remaining);
+        }
 
-    for _ in 0..number_of_blinks {
-        blink(&mut stones);
+        remaining
+            .values()
+            .fold(total, |acc, &n| acc.saturating_add(n))
     }
 
-    stones.values().sum()
-}
+    // number of distinct stone values in each generation, starting with the
+    // stones as given
+    pub fn distinct_per_generation(&self, stones: &HashMap<u128, u128>, blinks: u64) -> Vec<usize> {
+        let mut stones = stones.clone();
+        let mut result = vec![stones.len()];
 
-pub fn part2(data: &str) -> i64 {
-    let mut stones = parse(data);
-
-    let number_of_blinks = 75;
+        for _ in 0..blinks {
+            stones = self.blink(&stones);
+            result.push(stones.len());
+        }
 
-    for _ in 0..number_of_blinks {
-        blink(&mut stones);
+        result
     }
+}
+
+pub fn stones_after(data: &str, blinks: u64) -> u128 {
+    RuleEngine::standard().count_all(&parse(data), blinks)
+}
+
+pub fn part1(data: &str) -> u128 {
+    stones_after(data, 25)
+}
 
-    stones.values().sum()
+pub fn part2(data: &str) -> u128 {
+    stones_after(data, 75)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
     use super::*;
 
     //#[test]
@@ -96,4 +171,48 @@ mod tests {
         let data = "125 17";
         assert_eq!(part2(data), 65601038650482);
     }
+
+    #[test]
+    fn rule_engine_works() {
+        let engine = RuleEngine::standard();
+        assert_eq!(engine.count(125, 6) + engine.count(17, 6), 22);
+        assert_eq!(
+            engine.distinct_per_generation(&parse("125 17"), 3),
+            vec![2, 3, 4, 5]
+        );
+
+        let doubling = RuleEngine::new().with_rule(|stone| Some(vec![stone, stone]));
+        assert_eq!(doubling.count(7, 100), 1 << 100);
+        assert_eq!(doubling.count(7, 200), u128::MAX);
+    }
+
+    #[test]
+    fn memo_is_reused() {
+        let rewrites = Rc::new(Cell::new(0));
+        let counter = rewrites.clone();
+        let engine = RuleEngine::new().with_rule(move |stone| {
+            counter.set(counter.get() + 1);
+            Some(vec![stone + 1, stone + 2])
+        });
+
+        assert_eq!(engine.count(0, 10), 1 << 10);
+        let first = rewrites.get();
+        assert_eq!(engine.count(0, 10), 1 << 10);
+        assert_eq!(engine.count(1, 9), 1 << 9);
+        assert_eq!(rewrites.get(), first);
+
+        assert_eq!(
+            engine.count_all(&HashMap::from([(0, 3), (5, 1)]), 10),
+            4 << 10
+        );
+    }
+
+    #[test]
+    fn large_stones_work() {
+        // 17 digits, so it's multiplied past u64::MAX before it splits
+        let engine = RuleEngine::standard();
+        assert_eq!(engine.count(10_000_000_000_000_001, 1), 1);
+        assert_eq!(engine.count(10_000_000_000_000_001, 3), 4);
+        assert_eq!(stones_after("10000000000000001 10000000000000001", 3), 8);
+    }
 }
@@ -1,6 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
-use aoc2024::{Position, Direction};
+use aoc2024::components::{self, Connectivity};
+use aoc2024::{Direction, Position};
 
 struct Map {
     tiles: Vec<Vec<char>>,
@@ -29,170 +30,183 @@ fn parse(data: &str) -> Map {
     }
 }
 
-fn explore_region(start: &Position, map: &Map, visited: &mut HashSet<Position>) {
-    for direction in Direction::iterator() {
-        let new_position = start + direction.advance_by();
-        if map.in_bounds(&new_position)
-            && !visited.contains(&new_position)
-            && map.at(&new_position) == map.at(start)
-        {
-            visited.insert(new_position);
-            explore_region(&new_position, map, visited);
-        }
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub id: usize,
+    pub plant: char,
+    pub area: i64,
+    pub perimeter: i64,
+    pub sides: i64,
+    pub min: Position,
+    pub max: Position,
+}
+
+// the regions from one labelling pass, along with which region each cell
+// ended up in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Garden {
+    pub labels: Vec<Vec<usize>>,
+    pub regions: Vec<Region>,
 }
 
-fn explore(map: &Map) -> HashMap<Position, HashSet<Position>> {
-    let mut visited: HashSet<Position> = HashSet::new();
-    let mut regions: HashMap<Position, HashSet<Position>> = HashMap::new();
+// the holes in one region and the ids of every region sitting inside them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holes {
+    pub region: usize,
+    pub count: usize,
+    pub inner: Vec<usize>,
+}
 
-    for (x, row) in map.tiles.iter().enumerate() {
-        for (y, _) in row.iter().enumerate() {
-            let position = Position {
-                x: x as i64,
-                y: y as i64,
+const DIAGONALS: [(Direction, Direction); 4] = [
+    (Direction::North, Direction::East),
+    (Direction::East, Direction::South),
+    (Direction::South, Direction::West),
+    (Direction::West, Direction::North),
+];
+
+// the labeller does the flood fill, then each region's area, perimeter and
+// corners (a polygon has as many sides as corners) come from its cells
+fn label(map: &Map) -> Garden {
+    let components = components::label(&map.tiles, Connectivity::Four, |a, b| a == b);
+
+    let same =
+        |position: &Position, plant: char| map.in_bounds(position) && map.at(position) == plant;
+
+    let regions = components
+        .cells
+        .iter()
+        .enumerate()
+        .map(|(id, cells)| {
+            let plant = map.at(&cells[0]);
+            let mut region = Region {
+                id,
+                plant,
+                area: cells.len() as i64,
+                perimeter: 0,
+                sides: 0,
+                min: cells[0],
+                max: cells[0],
             };
 
-            if !visited.contains(&position) {
-                regions.insert(position, HashSet::new());
-                let mut region = regions.get_mut(&position).unwrap();
-                region.insert(position);
-                explore_region(&position, &map, &mut region);
-                visited.extend(region.iter());
+            for &position in cells {
+                region.min = Position {
+                    x: region.min.x.min(position.x),
+                    y: region.min.y.min(position.y),
+                };
+                region.max = Position {
+                    x: region.max.x.max(position.x),
+                    y: region.max.y.max(position.y),
+                };
+
+                for direction in Direction::iterator() {
+                    if !same(&(position + direction.advance_by()), plant) {
+                        region.perimeter += 1;
+                    }
+                }
+
+                for (a, b) in DIAGONALS {
+                    let side_a = same(&(position + a.advance_by()), plant);
+                    let side_b = same(&(position + b.advance_by()), plant);
+                    let diagonal = same(&(position + a.advance_by() + b.advance_by()), plant);
+
+                    if (!side_a && !side_b) || (side_a && side_b && !diagonal) {
+                        region.sides += 1;
+                    }
+                }
             }
-        }
-    }
 
-    regions
-}
+            region
+        })
+        .collect();
 
-fn region_area(region: &HashSet<Position>) -> i64 {
-    region.len() as i64
+    Garden {
+        labels: components.labels,
+        regions,
+    }
 }
 
-fn region_perimeter(region: &HashSet<Position>, map: &Map) -> i64 {
-    let mut perimeter: i64 = 0;
+// labels everything that isn't the region inside its bounding box (plus a
+// border of outside), whatever can't reach the border is a hole. The region
+// is 4-connected so the rest is labelled 8-connected
+fn find_holes(region: &Region, labels: &[Vec<usize>]) -> Holes {
+    let min = region.min + Position { x: -1, y: -1 };
+    let height = (region.max.x - region.min.x + 3) as usize;
+    let width = (region.max.y - region.min.y + 3) as usize;
+
+    let original = |x: usize, y: usize| {
+        let (x, y) = (x as i64 + min.x, y as i64 + min.y);
+        let in_bounds =
+            x >= 0 && y >= 0 && (x as usize) < labels.len() && (y as usize) < labels[0].len();
+        in_bounds.then(|| labels[x as usize][y as usize])
+    };
 
-    for position in region {
-        for direction in Direction::iterator() {
-            let new_position = position + direction.advance_by();
-            if !map.in_bounds(&new_position) || map.at(&new_position) != map.at(&position) {
-                perimeter += 1;
-            }
+    let is_region: Vec<Vec<bool>> = (0..height)
+        .map(|x| {
+            (0..width)
+                .map(|y| original(x, y) == Some(region.id))
+                .collect()
+        })
+        .collect();
+    let components = components::label(&is_region, Connectivity::Eight, |a, b| a == b);
+
+    let mut inner: HashSet<usize> = HashSet::new();
+    let mut count = 0;
+
+    for cells in components.cells.iter() {
+        let (cx, cy) = (cells[0].x as usize, cells[0].y as usize);
+        let enclosed = !is_region[cx][cy]
+            && cells.iter().all(|cell| {
+                cell.x > 0 && cell.y > 0 && cell.x < height as i64 - 1 && cell.y < width as i64 - 1
+            });
+
+        if enclosed {
+            count += 1;
+            inner.extend(
+                cells
+                    .iter()
+                    .filter_map(|cell| original(cell.x as usize, cell.y as usize)),
+            );
         }
     }
 
-    perimeter
-}
+    let mut inner: Vec<usize> = inner.into_iter().collect();
+    inner.sort();
 
-fn calculate_edges(
-    direction: Direction,
-    region: &HashSet<Position>,
-    min_p: &Position,
-    max_p: &Position,
-    map: &Map,
-) -> i64 {
-    let mut perimeter: i64 = 0;
-
-    if (direction == Direction::North) || (direction == Direction::South) {
-        for x in min_p.x..=max_p.x {
-            let mut y = min_p.y;
-            //do bottoms then tops
-            while y <= max_p.y {
-                let position = Position { x, y };
-                let to_check = position + direction.advance_by();
-                if region.contains(&position)
-                    && (!map.in_bounds(&to_check) || !region.contains(&to_check))
-                {
-                    perimeter += 1;
-                    let mut position = Position { x, y };
-                    let mut to_check = position + direction.advance_by();
-                    while y <= max_p.y
-                        && region.contains(&position)
-                        && (!map.in_bounds(&to_check) || !region.contains(&to_check))
-                    {
-                        y += 1;
-                        position = Position { x, y };
-                        to_check = position + direction.advance_by();
-                    }
-                } else {
-                    y += 1;
-                };
-            }
-        }
-    } else {
-        for y in min_p.y..=max_p.y {
-            let mut x = min_p.x;
-            while x <= max_p.x {
-                let position = Position { x, y };
-                let to_check = position + direction.advance_by();
-                if region.contains(&position)
-                    && (!map.in_bounds(&to_check) || !region.contains(&to_check))
-                {
-                    let mut position = Position { x, y };
-                    let mut to_check = position + direction.advance_by();
-                    while x <= max_p.x
-                        && region.contains(&position)
-                        && (!map.in_bounds(&to_check) || !region.contains(&to_check))
-                    {
-                        x += 1;
-                        position = Position { x, y };
-                        to_check = position + direction.advance_by();
-                    }
-                    perimeter += 1;
-                } else {
-                    x += 1;
-                };
-            }
-        }
+    Holes {
+        region: region.id,
+        count,
+        inner,
     }
-
-    perimeter
 }
 
-fn region_perimeter_discounted(region: &HashSet<Position>, map: &Map) -> i64 {
-    assert!(region.len() > 0);
-
-    let min_p = Position {
-        x: region.iter().map(|p| p.x).min().unwrap(),
-        y: region.iter().map(|p| p.y).min().unwrap(),
-    };
-
-    let max_p = Position {
-        x: region.iter().map(|p| p.x).max().unwrap(),
-        y: region.iter().map(|p| p.y).max().unwrap(),
-    };
+impl Garden {
+    // worked out on demand from the labels rather than with the regions,
+    // since it labels the region's bounding box again
+    pub fn holes(&self, region: usize) -> Holes {
+        find_holes(&self.regions[region], &self.labels)
+    }
+}
 
-    // first sort by (x, y) - this is the default
-    let mut perimeter: i64 = 0;
-    perimeter += calculate_edges(Direction::East, region, &min_p, &max_p, map);
-    perimeter += calculate_edges(Direction::West, region, &min_p, &max_p, map);
-    perimeter += calculate_edges(Direction::South, region, &min_p, &max_p, map);
-    perimeter += calculate_edges(Direction::North, region, &min_p, &max_p, map);
+pub fn garden(data: &str) -> Garden {
+    label(&parse(data))
+}
 
-    perimeter
+pub fn regions(data: &str) -> Vec<Region> {
+    garden(data).regions
 }
 
 pub fn part1(data: &str) -> i64 {
-    let map = parse(data);
-    let regions = explore(&map);
-
-    let result: i64 = regions
-        .values()
-        .map(|values| region_area(values) * region_perimeter(values, &map))
-        .sum();
-    result
+    regions(data)
+        .iter()
+        .map(|region| region.area * region.perimeter)
+        .sum()
 }
 
 pub fn part2(data: &str) -> i64 {
-    let map = parse(data);
-    let regions = explore(&map);
-
-    let result: i64 = regions
-        .values()
-        .map(|values| region_area(values) * region_perimeter_discounted(values, &map))
-        .sum();
-    result
+    regions(data)
+        .iter()
+        .map(|region| region.area * region.sides)
+        .sum()
 }
 
 #[cfg(test)]
@@ -258,4 +272,37 @@ MIIISIJEEE
 MMMISSJEEE";
         assert_eq!(part2(data), 1206);
     }
+
+    #[test]
+    fn regions_work() {
+        let data = "AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA";
+        let garden = garden(data);
+        let regions = &garden.regions;
+        assert_eq!(regions.len(), 3);
+
+        let outer = &regions[0];
+        assert_eq!(outer.plant, 'A');
+        assert_eq!((outer.area, outer.perimeter, outer.sides), (28, 40, 12));
+        assert_eq!(
+            (outer.min, outer.max),
+            (Position { x: 0, y: 0 }, Position { x: 5, y: 5 })
+        );
+        assert_eq!((regions[1].area, regions[1].sides), (4, 4));
+
+        // the two B regions touch diagonally so they sit in the same hole
+        assert_eq!(
+            garden.holes(0),
+            Holes {
+                region: 0,
+                count: 1,
+                inner: vec![1, 2]
+            }
+        );
+        assert_eq!(garden.holes(1).count, 0);
+    }
 }
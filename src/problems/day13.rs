@@ -1,6 +1,7 @@
 use regex::Regex;
 
-use aoc2024::{Position, lcm};
+use aoc2024::number_theory::extended_gcd_i128;
+use aoc2024::Position;
 
 #[derive(Debug)]
 struct ClawMachine {
@@ -40,58 +41,96 @@ fn parse(data: &str) -> Vec<ClawMachine> {
         .collect()
 }
 
-fn solve_machine(machine: &ClawMachine) -> Option<(i64, i64)> {
-    let lcm_a = lcm(machine.button_a.x, machine.button_a.y);
+// cheapest non-negative a, b with a * u + b * v = w
+fn solve_line(u: i128, v: i128, w: i128, cost_a: i128, cost_b: i128) -> Option<(i128, i128)> {
+    match (u, v) {
+        (0, 0) => return (w == 0).then_some((0, 0)),
+        (0, _) => return (w % v == 0 && w / v >= 0).then_some((0, w / v)),
+        (_, 0) => return (w % u == 0 && w / u >= 0).then_some((w / u, 0)),
+        _ => (),
+    }
+
+    let (g, x, y) = extended_gcd_i128(u, v)?;
+    if w % g != 0 {
+        return None;
+    }
+
+    // every solution is (a0 + k * step_a, b0 - k * step_b)
+    let (a0, b0) = (x * (w / g), y * (w / g));
+    let (step_a, step_b) = (v / g, u / g);
 
-    let fac = (lcm_a / machine.button_a.x) * machine.button_b.x
-        - (lcm_a / machine.button_a.y) * machine.button_b.y;
+    let lowest = (-a0).div_euclid(step_a) + i128::from((-a0).rem_euclid(step_a) != 0);
+    let highest = b0.div_euclid(step_b);
+    if lowest > highest {
+        return None;
+    }
+
+    let k = match cost_a * step_a - cost_b * step_b > 0 {
+        true => lowest,
+        false => highest,
+    };
+
+    Some((a0 + k * step_a, b0 - k * step_b))
+}
 
-    let prize = (lcm_a / machine.button_a.x) * machine.prize.x
-        - (lcm_a / machine.button_a.y) * machine.prize.y;
+fn solve_machine(machine: &ClawMachine, cost_a: i64, cost_b: i64) -> Option<(i64, i64)> {
+    let (ax, ay) = (machine.button_a.x as i128, machine.button_a.y as i128);
+    let (bx, by) = (machine.button_b.x as i128, machine.button_b.y as i128);
+    let (px, py) = (machine.prize.x as i128, machine.prize.y as i128);
 
-    if prize % fac == 0 {
-        let b = prize / fac;
+    let det = ax * by - ay * bx;
+    if det != 0 {
+        // cramer's rule, only whole non-negative presses count
+        let a = px * by - py * bx;
+        let b = ax * py - ay * px;
 
-        if (machine.prize.x - b * machine.button_b.x) % machine.button_a.x == 0 {
-            let a = (machine.prize.x - b * machine.button_b.x) / machine.button_a.x;
-            return Some((a, b));
+        if a % det != 0 || b % det != 0 || a / det < 0 || b / det < 0 {
+            return None;
         }
+
+        return Some(((a / det) as i64, (b / det) as i64));
     }
 
-    None
-}
+    // the buttons are collinear, so the prize has to be on the same line and
+    // it comes down to one equation along whichever axis the buttons move
+    if ax * py - ay * px != 0 || bx * py - by * px != 0 {
+        return None;
+    }
 
-pub fn part1(data: &str) -> i64 {
-    let machines = parse(data);
+    let (a, b) = match (ax, bx) {
+        (0, 0) => solve_line(ay, by, py, cost_a as i128, cost_b as i128)?,
+        _ => solve_line(ax, bx, px, cost_a as i128, cost_b as i128)?,
+    };
 
-    let result = machines
-        .iter()
-        .map(|machine| {
-            let (a, b) = solve_machine(machine).unwrap_or((0, 0));
-            3 * a + b
-        })
-        .sum();
-    result
+    if a * ax + b * bx != px || a * ay + b * by != py {
+        return None;
+    }
+
+    Some((a as i64, b as i64))
 }
 
-pub fn part2(data: &str) -> i64 {
+pub fn min_tokens(data: &str, cost_a: i64, cost_b: i64, offset: i64) -> i64 {
     let mut machines = parse(data);
 
     for machine in machines.iter_mut() {
-        machine.prize.x += 10000000000000;
-        machine.prize.y += 10000000000000;
+        machine.prize.x += offset;
+        machine.prize.y += offset;
     }
 
-    //failed: 98958951401149
-
-    let result = machines
+    machines
         .iter()
-        .map(|machine| {
-            let (a, b) = solve_machine(machine).unwrap_or((0, 0));
-            3 * a + b
-        })
-        .sum();
-    result
+        .filter_map(|machine| solve_machine(machine, cost_a, cost_b))
+        .map(|(a, b)| cost_a * a + cost_b * b)
+        .sum()
+}
+
+pub fn part1(data: &str) -> i64 {
+    min_tokens(data, 3, 1, 0)
+}
+
+pub fn part2(data: &str) -> i64 {
+    //failed: 98958951401149
+    min_tokens(data, 3, 1, 10000000000000)
 }
 
 #[cfg(test)]
@@ -145,4 +184,23 @@ Button B: X+11, Y+90
 Prize: X=4103, Y=11529";
         assert_eq!(part2(data), 0);
     }
+
+    #[test]
+    fn collinear_buttons_work() {
+        let data = "Button A: X+2, Y+4
+Button B: X+3, Y+6
+Prize: X=12, Y=24
+
+Button A: X+4, Y+4
+Button B: X+6, Y+6
+Prize: X=7, Y=7
+
+Button A: X+2, Y+4
+Button B: X+3, Y+6
+Prize: X=12, Y=25";
+        // 4 presses of B beat 6 of A or 3 of A and 2 of B
+        assert_eq!(min_tokens(data, 3, 1, 0), 4);
+        // but with A cheap, 6 presses of A wins
+        assert_eq!(min_tokens(data, 1, 3, 0), 6);
+    }
 }
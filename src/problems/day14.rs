@@ -1,4 +1,10 @@
-use aoc2024::Position;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use aoc2024::number_theory::{checked_lcm, crt, linear_congruence};
+use aoc2024::{gcd, Position};
 use regex::Regex;
 
 struct Robot {
@@ -11,6 +17,7 @@ fn parse(data: &str) -> Vec<Robot> {
 
     data.trim()
         .split("\n")
+        .filter(|line| !line.starts_with("room="))
         .map(|line| {
             let robot_match = re.captures(line).unwrap();
             Robot {
@@ -35,147 +42,280 @@ fn advance_robot(robot: &Robot, map_dim: &Position, t: i64) -> Position {
     }
 }
 
-fn count_robots(start: &Position, end: &Position, robots: &Vec<Position>) -> i64 {
-    let mut count: i64 = 0;
-    for &robot in robots.iter() {
-        if robot.x >= start.x && robot.x <= end.x && robot.y >= start.y && robot.y <= end.y {
-            count += 1;
-        }
+// an optional first line of `room=11,7` gives the size, otherwise it's the
+// smallest odd size that fits every robot
+pub fn room_size(data: &str) -> Position {
+    let re = Regex::new(r"^room=(?<x>\d+),(?<y>\d+)").unwrap();
+    if let Some(room_match) = re.captures(data.trim()) {
+        return Position {
+            x: room_match["x"].parse().unwrap(),
+            y: room_match["y"].parse().unwrap(),
+        };
     }
 
-    count
+    let robots = parse(data);
+    let odd = |size: i64| size + 1 - size % 2;
+    Position {
+        x: odd(robots.iter().map(|robot| robot.p.x).max().unwrap_or(0) + 1),
+        y: odd(robots.iter().map(|robot| robot.p.y).max().unwrap_or(0) + 1),
+    }
 }
 
-pub fn part1(data: &str) -> i64 {
+// robots on either middle line don't count towards any quadrant
+pub fn safety_factor(data: &str, map_dim: &Position, seconds: i64) -> i64 {
+    assert!(
+        map_dim.x % 2 == 1 && map_dim.y % 2 == 1,
+        "Room dimensions must be odd"
+    );
+
     let robots = parse(data);
-    let map_dim = Position { x: 101, y: 103 };
-    // let map_dim = Position { x: 11, y: 7 };
     let midpoint = Position {
-        x: (&map_dim.x - 1) / 2,
-        y: (&map_dim.y - 1) / 2,
+        x: (map_dim.x - 1) / 2,
+        y: (map_dim.y - 1) / 2,
     };
 
-    let final_positions: Vec<Position> = robots
-        .iter()
-        .map(|robot| advance_robot(&robot, &map_dim, 100))
-        .filter(|&position| position.x != midpoint.x && position.y != midpoint.y)
-        .collect();
-
-    let quad_a_start = Position { x: 0, y: 0 };
+    let mut quadrants = [0i64; 4];
+    for robot in robots.iter() {
+        let position = advance_robot(robot, map_dim, seconds);
+        if position.x == midpoint.x || position.y == midpoint.y {
+            continue;
+        }
 
-    let quad_b_start = Position {
-        x: 0,
-        y: midpoint.y,
-    };
+        let quadrant = match (position.x < midpoint.x, position.y < midpoint.y) {
+            (true, true) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (false, false) => 3,
+        };
+        quadrants[quadrant] += 1;
+    }
 
-    let quad_b_end = Position {
-        x: midpoint.x,
-        y: map_dim.y - 1,
-    };
+    quadrants.iter().product()
+}
 
-    let quad_c_start = Position {
-        x: midpoint.x,
-        y: 0,
-    };
+pub fn part1(data: &str) -> i64 {
+    safety_factor(data, &room_size(data), 100)
+}
 
-    let quad_c_end = Position {
-        x: map_dim.x - 1,
-        y: midpoint.y,
-    };
+// every second the robot is on `cell`, as the first one and the step between
+// them. each axis is a linear congruence in t and the two are joined with CRT
+fn visits(robot: &Robot, map_dim: &Position, cell: &Position) -> Option<(i64, i64)> {
+    let x = linear_congruence(robot.v.x, cell.x - robot.p.x, map_dim.x)?;
+    let y = linear_congruence(robot.v.y, cell.y - robot.p.y, map_dim.y)?;
 
-    let quad_d_end = Position {
-        x: &map_dim.x - 1,
-        y: &map_dim.y - 1,
-    };
+    crt(&[x, y])
+}
 
-    let q1 = count_robots(&quad_a_start, &midpoint, &final_positions);
-    let q2 = count_robots(&quad_b_start, &quad_b_end, &final_positions);
-    let q3 = count_robots(&quad_c_start, &quad_c_end, &final_positions);
-    let q4 = count_robots(&midpoint, &quad_d_end, &final_positions);
+// each robot's x repeats every width / gcd(vx, width) seconds and its y
+// likewise, the room as a whole repeats once all of those line up. This
+// always divides the lcm of the dimensions, None if even that overflows
+pub fn period(data: &str, map_dim: &Position) -> Option<i64> {
+    parse(data).iter().try_fold(1, |period, robot| {
+        let x = map_dim.x / gcd(robot.v.x.rem_euclid(map_dim.x), map_dim.x);
+        let y = map_dim.y / gcd(robot.v.y.rem_euclid(map_dim.y), map_dim.y);
+        checked_lcm(checked_lcm(period, x)?, y)
+    })
+}
 
-    let result: i64 = q1 * q2 * q3 * q4;
-    result
+pub fn first_occupied(data: &str, map_dim: &Position, cell: &Position) -> Option<i64> {
+    parse(data)
+        .iter()
+        .filter_map(|robot| visits(robot, map_dim, cell))
+        .map(|(first, _)| first)
+        .min()
 }
 
-fn draw_robots(map_dim: &Position, robots: &Vec<Position>) -> () {
-    let mut result = Vec::new();
+// one flag per second of the period is kept, anything longer gives None
+const MAX_TRACKED_PERIOD: i64 = 1 << 26;
 
-    for _ in 0..map_dim.x {
-        let mut vec: Vec<char> = Vec::new();
-        for _ in 0..map_dim.y {
-            vec.push('.');
+// two robots meet when the one relative to the other visits the origin, so
+// every pair marks its meetings across one period and what's left over has
+// every robot on its own cell. The flags are a Vec<bool> as long as the
+// period, so up to 1 << 26 entries or about 64 MB
+pub fn collision_free(data: &str, map_dim: &Position) -> Option<Vec<i64>> {
+    let robots = parse(data);
+    let period = period(data, map_dim).filter(|&period| period <= MAX_TRACKED_PERIOD)?;
+    let mut collided = vec![false; period as usize];
+    let origin = Position { x: 0, y: 0 };
+
+    for (i, a) in robots.iter().enumerate() {
+        for b in robots[i + 1..].iter() {
+            let relative = Robot {
+                p: a.p - b.p,
+                v: a.v - b.v,
+            };
+            if let Some((first, step)) = visits(&relative, map_dim, &origin) {
+                for t in (first..period).step_by(step as usize) {
+                    collided[t as usize] = true;
+                }
+            }
         }
-        result.push(vec);
     }
 
-    for robot in robots {
-        result[robot.x as usize][robot.y as usize] = 'x';
-    }
-
-    for x in 0..map_dim.x {
-        for y in 0..map_dim.y {
-            print!("{}", result[x as usize][y as usize]);
-        }
-        print!("\n");
-    }
-}
-
-fn find_run(robots: &Vec<Position>, overrun: i64) -> bool {
-    assert!(robots.len() > 1);
-
-    let mut i = 1;
-    while i < robots.len() {
-        let mut robot_prev = robots[i - 1];
-        let mut robot_curr = robots[i];
-        let mut run = 1;
-        while (i < robots.len())
-            && (robot_prev.x == robot_curr.x)
-            && (robot_curr.y - robot_prev.y == 1)
-        {
-            robot_prev = robots[i - 1];
-            robot_curr = robots[i];
-            run += 1;
-            i += 1;
-        }
+    Some((0..period).filter(|&t| !collided[t as usize]).collect())
+}
 
-        if run >= overrun {
-            println!("{robot_prev:?}|{run}");
-            return true;
-        }
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Score {
+    Variance,
+    Entropy,
+    SafetyFactor,
+}
 
-        if run == 1 {
-            i += 1;
+impl Score {
+    // how spread out one axis of a frame is, lower is more ordered
+    fn axis(&self, values: &[i64], size: i64) -> f64 {
+        let n = values.len() as f64;
+        match self {
+            Score::Variance => {
+                let mean = values.iter().sum::<i64>() as f64 / n;
+                values
+                    .iter()
+                    .map(|&value| (value as f64 - mean).powi(2))
+                    .sum::<f64>()
+                    / n
+            }
+            Score::Entropy => {
+                let mut counts = vec![0usize; size as usize];
+                for &value in values {
+                    counts[value as usize] += 1;
+                }
+                counts
+                    .iter()
+                    .filter(|&&count| count > 0)
+                    .map(|&count| {
+                        let p = count as f64 / n;
+                        -p * p.log2()
+                    })
+                    .sum()
+            }
+            Score::SafetyFactor => {
+                let midpoint = (size - 1) / 2;
+                let below = values.iter().filter(|&&value| value < midpoint).count();
+                let above = values.iter().filter(|&&value| value > midpoint).count();
+                (below * above) as f64
+            }
         }
     }
+}
 
-    false
+pub fn positions(data: &str, map_dim: &Position, seconds: i64) -> Vec<Position> {
+    parse(data)
+        .iter()
+        .map(|robot| advance_robot(robot, map_dim, seconds))
+        .collect()
 }
 
-pub fn part2(data: &str) -> i64 {
+// x positions repeat every width seconds and y positions every height, so
+// the most ordered offset on each axis can be found on its own and the two
+// combined into the one second where both line up
+pub fn easter_egg(data: &str, map_dim: &Position, score: Score) -> Option<i64> {
     let robots = parse(data);
-    let map_dim = Position { x: 101, y: 103 };
+    if robots.is_empty() {
+        return None;
+    }
 
-    for i in 0..=10000 {
-        let mut positions: Vec<Position> = robots
-            .iter()
-            .map(|robot| advance_robot(&robot, &map_dim, i))
-            .collect();
-        positions.sort();
+    let best = |size: i64, axis: fn(&Position) -> i64| {
+        (0..size)
+            .map(|t| {
+                let values: Vec<i64> = robots
+                    .iter()
+                    .map(|robot| axis(&advance_robot(robot, map_dim, t)))
+                    .collect();
+                (t, score.axis(&values, size))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap()
+            .0
+    };
+
+    let (second, _) = crt(&[
+        (best(map_dim.x, |position| position.x), map_dim.x),
+        (best(map_dim.y, |position| position.y), map_dim.y),
+    ])?;
+
+    Some(second)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Image {
+    Pbm,
+    Pgm,
+}
 
-        if i % 10000 == 0 {
-            println!("Iteration {i}");
+impl Image {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Image::Pbm => "pbm",
+            Image::Pgm => "pgm",
         }
+    }
+}
+
+// binary netpbm with every cell drawn as a scale x scale block. P4 packs each
+// row into bits with 1 (black) wherever there's a robot, P5 is a byte per
+// pixel that gets darker the more robots share the cell
+pub fn image(positions: &[Position], map_dim: &Position, format: Image, scale: usize) -> Vec<u8> {
+    assert!(scale > 0, "Scale must be at least 1");
 
-        if find_run(&positions, 10) {
-            println!("---------------------------------------------------");
-            draw_robots(&map_dim, &positions);
-            println!("Iteration {i}!");
-            println!("---------------------------------------------------");
-            break;
+    let (width, height) = (map_dim.x as usize, map_dim.y as usize);
+    let mut counts = vec![0usize; width * height];
+    for position in positions {
+        counts[position.y as usize * width + position.x as usize] += 1;
+    }
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    let (image_width, image_height) = (width * scale, height * scale);
+    let mut result = match format {
+        Image::Pbm => format!("P4\n{} {}\n", image_width, image_height),
+        Image::Pgm => format!("P5\n{} {}\n255\n", image_width, image_height),
+    }
+    .into_bytes();
+
+    for row in 0..image_height {
+        let cells = &counts[row / scale * width..][..width];
+        let pixels = (0..image_width).map(|col| cells[col / scale]);
+
+        match format {
+            Image::Pbm => {
+                let mut bytes = vec![0u8; image_width.div_ceil(8)];
+                for (col, count) in pixels.enumerate() {
+                    if count > 0 {
+                        bytes[col / 8] |= 0x80 >> (col % 8);
+                    }
+                }
+                result.extend(bytes);
+            }
+            Image::Pgm => result.extend(pixels.map(|count| (255 - count * 255 / most) as u8)),
         }
     }
 
-    0
+    result
+}
+
+// one file per second named by its time step so they sort in order
+pub fn export_frames(
+    data: &str,
+    map_dim: &Position,
+    seconds: RangeInclusive<i64>,
+    format: Image,
+    scale: usize,
+    dir: &Path,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    seconds
+        .map(|t| {
+            let path = dir.join(format!("frame{:05}.{}", t, format.extension()));
+            let positions = positions(data, map_dim, t);
+            fs::write(&path, image(&positions, map_dim, format, scale))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+pub fn part2(data: &str) -> i64 {
+    easter_egg(data, &room_size(data), Score::Variance).expect("No Easter egg found")
 }
 
 #[cfg(test)]
@@ -196,12 +336,133 @@ p=9,3 v=2,3
 p=7,3 v=-1,2
 p=2,4 v=2,-3
 p=9,5 v=-3,-3";
+        assert_eq!(part1(data), 12);
+    }
+
+    #[test]
+    fn part1_works_with_room_header() {
+        let data = "room=101,103
+p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3";
+        assert_eq!(room_size(data), Position { x: 101, y: 103 });
         assert_eq!(part1(data), 21);
     }
 
     #[test]
     fn part1_works_on_midpoint() {
-        let data = "p=0,2 v=1,1";
+        let data = "room=101,103
+p=0,2 v=1,1";
         assert_eq!(part1(data), 0);
     }
+
+    #[test]
+    fn easter_egg_works() {
+        // a 10x10 block of robots at 1234 seconds, scattered everywhere else
+        let map_dim = Position { x: 101, y: 103 };
+        let data: Vec<String> = (0..100)
+            .map(|i: i64| {
+                let target = Position {
+                    x: 10 + i % 10,
+                    y: 60 + i / 10,
+                };
+                let v = Position {
+                    x: (i * 37) % 99 - 49,
+                    y: (i * 53) % 97 - 48,
+                };
+                let p = target - 1234 * v;
+                format!(
+                    "p={},{} v={},{}",
+                    p.x.rem_euclid(map_dim.x),
+                    p.y.rem_euclid(map_dim.y),
+                    v.x,
+                    v.y
+                )
+            })
+            .collect();
+        let data = format!("room=101,103\n{}", data.join("\n"));
+
+        for score in [Score::Variance, Score::Entropy, Score::SafetyFactor] {
+            assert_eq!(easter_egg(&data, &map_dim, score), Some(1234));
+        }
+        assert_eq!(part2(&data), 1234);
+    }
+
+    #[test]
+    fn image_works() {
+        let map_dim = Position { x: 3, y: 2 };
+        let positions = [
+            Position { x: 0, y: 0 },
+            Position { x: 2, y: 1 },
+            Position { x: 2, y: 1 },
+        ];
+
+        let mut expected = b"P4\n6 4\n".to_vec();
+        expected.extend([0b11000000, 0b11000000, 0b00001100, 0b00001100]);
+        assert_eq!(image(&positions, &map_dim, Image::Pbm, 2), expected);
+
+        let mut expected = b"P5\n3 2\n255\n".to_vec();
+        expected.extend([128, 255, 255, 255, 255, 0]);
+        assert_eq!(image(&positions, &map_dim, Image::Pgm, 1), expected);
+    }
+
+    #[test]
+    fn trajectory_queries_work() {
+        let data = "p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3";
+        let map_dim = Position { x: 11, y: 7 };
+        assert_eq!(period(data, &map_dim), Some(77));
+        assert_eq!(
+            collision_free(data, &map_dim).unwrap()[..8],
+            [1, 3, 6, 8, 12, 17, 18, 19]
+        );
+        assert_eq!(
+            first_occupied(data, &map_dim, &Position { x: 3, y: 2 }),
+            Some(3)
+        );
+
+        // velocities sharing a factor with the dimensions repeat well before
+        // lcm(12, 8) = 24 seconds
+        let map_dim = Position { x: 12, y: 8 };
+        assert_eq!(period(data, &map_dim), Some(24));
+        assert_eq!(period("p=0,0 v=4,4", &map_dim), Some(6));
+        assert_eq!(
+            first_occupied("p=0,0 v=4,4", &map_dim, &Position { x: 1, y: 0 }),
+            None
+        );
+
+        // too long to track second by second, or to fit in an i64 at all
+        let map_dim = Position {
+            x: 1_000_003,
+            y: 1_000_033,
+        };
+        assert_eq!(period("p=0,0 v=1,1", &map_dim), Some(1_000_036_000_099));
+        assert_eq!(collision_free("p=0,0 v=1,1", &map_dim), None);
+
+        let map_dim = Position {
+            x: i64::MAX,
+            y: i64::MAX - 1,
+        };
+        assert_eq!(period("p=0,0 v=1,1", &map_dim), None);
+        assert_eq!(collision_free("p=0,0 v=1,1", &map_dim), None);
+    }
 }
@@ -1,46 +1,48 @@
+use std::io::{BufRead, Write};
+
 use aoc2024::{Move, Position};
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 enum Tile {
-    Empty = 0,
-    Robot = 1,
-    Box = 2,
-    Wall = 3,
-    BoxLeft = 4,
-    BoxRight = 5,
+    Empty,
+    Wall,
+    Box(usize),
 }
 
-struct Game {
-    tile_map: Vec<Vec<Tile>>,
-    moves: Vec<Move>,
-    robot: Position,
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Crate {
+    pub id: usize,
+    pub position: Position,
+    pub width: i64,
+}
+
+impl Crate {
+    fn cells(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.width).map(|dy| self.position + Position { x: 0, y: dy })
+    }
 }
 
-fn at<'a>(tile_map: &'a Vec<Vec<Tile>>, position: &Position) -> &'a Tile {
-    &tile_map[position.x as usize][position.y as usize]
+struct Game {
+    grid: Vec<Vec<char>>,
+    moves: Vec<Move>,
 }
 
 fn parse(data: &str) -> Game {
     let game: Vec<&str> = data.trim().split("\n\n").collect();
 
-    let tile_map: Vec<Vec<Tile>> = game[0]
+    let grid: Vec<Vec<char>> = game[0]
         .split("\n")
-        .map(|line| {
-            line.chars()
-                .map(|ch| match ch {
-                    '.' => Tile::Empty,
-                    '@' => Tile::Robot,
-                    'O' => Tile::Box,
-                    '#' => Tile::Wall,
-                    _ => panic!("Invalid tile {ch}"),
-                })
-                .collect()
-        })
+        .map(|line| line.chars().collect())
         .collect();
 
-    let moves: Vec<Move> = game[1]
-        .chars()
-        .filter(|ch| *ch != '\n')
+    let moves: Vec<Move> = parse_moves(game.get(1).unwrap_or(&""));
+
+    Game { grid, moves }
+}
+
+fn parse_moves(data: &str) -> Vec<Move> {
+    data.chars()
+        .filter(|ch| !ch.is_whitespace())
         .map(|ch| match ch {
             '>' => Move::Right,
             '^' => Move::Up,
@@ -48,267 +50,331 @@ fn parse(data: &str) -> Game {
             '<' => Move::Left,
             _ => panic!("Invalid move {ch}"),
         })
-        .collect();
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Step {
+    pub mv: Move,
+    pub robot: Position,
+    pub moved: Vec<usize>,
+    pub blocked: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Warehouse {
+    tile_map: Vec<Vec<Tile>>,
+    crates: Vec<Crate>,
+    robot: Position,
+    history: Vec<Step>,
+}
+
+impl Warehouse {
+    // every tile of the original map is stretched `width` tiles wide, boxes
+    // become a single crate spanning all of them
+    fn new(grid: &[Vec<char>], width: usize) -> Warehouse {
+        assert!(width > 0);
 
-    let mut robot = None;
-    for x in 0..tile_map.len() {
-        for y in 0..tile_map[0].len() {
-            if tile_map[x][y] == Tile::Robot {
-                robot = Some(Position {
+        let mut tile_map = Vec::new();
+        let mut crates = Vec::new();
+        let mut robot = None;
+
+        for (x, line) in grid.iter().enumerate() {
+            let mut row = Vec::new();
+            for (y, ch) in line.iter().enumerate() {
+                let position = Position {
                     x: x as i64,
-                    y: y as i64,
-                });
+                    y: (y * width) as i64,
+                };
+                let tile = match ch {
+                    '.' => Tile::Empty,
+                    '#' => Tile::Wall,
+                    '@' => {
+                        robot = Some(position);
+                        Tile::Empty
+                    }
+                    'O' => {
+                        crates.push(Crate {
+                            id: crates.len(),
+                            position,
+                            width: width as i64,
+                        });
+                        Tile::Box(crates.len() - 1)
+                    }
+                    _ => panic!("Invalid tile {ch}"),
+                };
+                row.extend(std::iter::repeat_n(tile, width));
             }
+            tile_map.push(row);
+        }
+
+        let Some(robot) = robot else {
+            panic!("Robot not found!");
+        };
+
+        Warehouse {
+            tile_map,
+            crates,
+            robot,
+            history: Vec::new(),
         }
     }
 
-    let Some(robot) = robot else {
-        panic!("Robot not found!");
-    };
+    fn at(&self, position: &Position) -> Tile {
+        self.tile_map[position.x as usize][position.y as usize]
+    }
 
-    Game {
-        tile_map,
-        moves,
-        robot,
+    fn set(&mut self, position: &Position, tile: Tile) {
+        self.tile_map[position.x as usize][position.y as usize] = tile;
     }
-}
 
-fn can_move_robot(to_move: &Position, tile_map: &Vec<Vec<Tile>>, mv: &Move) -> bool {
-    let tile = *at(tile_map, to_move);
-    let next_pos = to_move + mv.advance_by();
+    pub fn robot(&self) -> Position {
+        self.robot
+    }
 
-    if *mv == Move::Up || *mv == Move::Down {
-        match tile {
-            Tile::Empty => true,
-            Tile::Robot => can_move_robot(&next_pos, tile_map, mv),
-            Tile::BoxLeft => {
-                let right_box = &next_pos + Move::Right.advance_by();
+    pub fn crates(&self) -> &[Crate] {
+        &self.crates
+    }
 
-                can_move_robot(&next_pos, tile_map, mv) && can_move_robot(&right_box, tile_map, mv)
-            }
-            Tile::BoxRight => {
-                let left_box = &next_pos + Move::Left.advance_by();
+    pub fn history(&self) -> &[Step] {
+        &self.history
+    }
 
-                can_move_robot(&next_pos, tile_map, mv) && can_move_robot(&left_box, tile_map, mv)
+    // every crate that would be shoved along by the robot moving in `mv`,
+    // or None if anything in the chain runs into a wall
+    fn pushed(&self, mv: Move) -> Option<Vec<usize>> {
+        let mut to_visit = vec![self.robot];
+        let mut moved: Vec<usize> = Vec::new();
+
+        while let Some(position) = to_visit.pop() {
+            let next = position + mv.advance_by();
+
+            match self.at(&next) {
+                Tile::Wall => return None,
+                Tile::Empty => (),
+                Tile::Box(id) if moved.contains(&id) => (),
+                Tile::Box(id) => {
+                    moved.push(id);
+                    to_visit.extend(self.crates[id].cells());
+                }
             }
-            Tile::Wall => false,
-            Tile::Box => can_move_robot(&next_pos, tile_map, mv),
-        }
-    } else {
-        match tile {
-            Tile::Empty => true,
-            Tile::Robot => can_move_robot(&next_pos, tile_map, mv),
-            Tile::BoxLeft => can_move_robot(&next_pos, tile_map, mv),
-            Tile::BoxRight => can_move_robot(&next_pos, tile_map, mv),
-            Tile::Wall => false,
-            Tile::Box => can_move_robot(&next_pos, tile_map, mv),
         }
+
+        Some(moved)
     }
-}
 
-fn move_robot(to_move: &Position, tile_map: &mut Vec<Vec<Tile>>, mv: &Move) {
-    let tile = *at(tile_map, to_move);
-    let next_pos = to_move + mv.advance_by();
-
-    if *mv == Move::Up || *mv == Move::Down {
-        match tile {
-            Tile::Empty | Tile::Wall => (),
-            Tile::Robot => {
-                move_robot(&next_pos, tile_map, mv);
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::Robot;
+    fn shift(&mut self, ids: &[usize], by: Position) {
+        for &id in ids {
+            for cell in self.crates[id].cells().collect::<Vec<_>>() {
+                self.set(&cell, Tile::Empty);
             }
-            Tile::BoxLeft => {
-                let right_box = to_move + Move::Right.advance_by();
-                let next_right = &next_pos + Move::Right.advance_by();
+        }
 
-                move_robot(&next_pos, tile_map, mv);
-                move_robot(&next_right, tile_map, mv);
+        for &id in ids {
+            self.crates[id].position = self.crates[id].position + by;
+            for cell in self.crates[id].cells().collect::<Vec<_>>() {
+                self.set(&cell, Tile::Box(id));
+            }
+        }
+    }
 
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[right_box.x as usize][right_box.y as usize] = Tile::Empty;
+    // every move goes in the history, a blocked one just leaves everything
+    // where it was, so history entries line up with the moves made
+    pub fn step(&mut self, mv: Move) -> bool {
+        let Some(moved) = self.pushed(mv) else {
+            self.history.push(Step {
+                mv,
+                robot: self.robot,
+                moved: Vec::new(),
+                blocked: true,
+            });
+            return false;
+        };
+
+        self.shift(&moved, mv.advance_by());
+
+        self.history.push(Step {
+            mv,
+            robot: self.robot,
+            moved,
+            blocked: false,
+        });
+        self.robot = self.robot + mv.advance_by();
+
+        true
+    }
 
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::BoxLeft;
-                tile_map[next_right.x as usize][next_right.y as usize] = Tile::BoxRight;
-            }
-            Tile::BoxRight => {
-                let left_box = to_move + Move::Left.advance_by();
-                let next_left = &next_pos + Move::Left.advance_by();
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.history.pop()?;
 
-                move_robot(&next_left, tile_map, mv);
-                move_robot(&next_pos, tile_map, mv);
+        if !step.blocked {
+            self.shift(&step.moved, -1 * step.mv.advance_by());
+            self.robot = step.robot;
+        }
 
-                tile_map[left_box.x as usize][left_box.y as usize] = Tile::Empty;
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
+        Some(step)
+    }
 
-                tile_map[next_left.x as usize][next_left.y as usize] = Tile::BoxLeft;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::BoxRight;
-            }
-            Tile::Box => {
-                move_robot(&next_pos, tile_map, mv);
+    pub fn render(&self) -> String {
+        self.tile_map
+            .iter()
+            .enumerate()
+            .map(|(x, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(y, tile)| {
+                        let position = Position {
+                            x: x as i64,
+                            y: y as i64,
+                        };
+                        match tile {
+                            _ if position == self.robot => '@',
+                            Tile::Empty => '.',
+                            Tile::Wall => '#',
+                            Tile::Box(id) => {
+                                let held = &self.crates[*id];
+                                match position.y - held.position.y {
+                                    _ if held.width == 1 => 'O',
+                                    0 => '[',
+                                    dy if dy == held.width - 1 => ']',
+                                    _ => '=',
+                                }
+                            }
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
 
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::Box;
-            }
+    // renders the warehouse as it was right after the move at `step` in the
+    // history, so the same index as the move itself
+    pub fn frame(&self, step: usize) -> String {
+        let mut warehouse = self.clone();
+        while warehouse.history.len() > step + 1 {
+            warehouse.undo();
         }
-    } else {
-        match tile {
-            Tile::Empty | Tile::Wall => (),
-            Tile::Robot => {
-                move_robot(&next_pos, tile_map, mv);
-
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::Robot;
-            }
-            Tile::BoxLeft => {
-                move_robot(&next_pos, tile_map, mv);
 
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::BoxLeft;
-            }
-            Tile::BoxRight => {
-                move_robot(&next_pos, tile_map, mv);
+        warehouse.render()
+    }
 
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::BoxRight;
-            }
-            Tile::Box => {
-                move_robot(&next_pos, tile_map, mv);
+    // steps through `moves` and returns the index of the first one whose
+    // resulting frame differs from `expected`, `frame` renders what it was
+    pub fn first_divergence(&self, moves: &[Move], expected: &[&str]) -> Option<usize> {
+        let mut warehouse = self.clone();
 
-                tile_map[to_move.x as usize][to_move.y as usize] = Tile::Empty;
-                tile_map[next_pos.x as usize][next_pos.y as usize] = Tile::Box;
+        for (i, (mv, frame)) in moves.iter().zip(expected).enumerate() {
+            warehouse.step(*mv);
+            if warehouse.render() != frame.trim() {
+                return Some(i);
             }
         }
+
+        None
     }
-}
 
-#[allow(dead_code)]
-fn print_game(game: &Game) {
-    for row in &game.tile_map {
-        for tile in row {
-            let ch = match tile {
-                Tile::Empty => '.',
-                Tile::Robot => '@',
-                Tile::Wall => '#',
-                Tile::Box => 'O',
-                Tile::BoxLeft => '[',
-                Tile::BoxRight => ']',
-            };
-            print!("{ch}");
-        }
-        print!("\n");
+    pub fn gps(&self) -> i64 {
+        self.crates
+            .iter()
+            .map(|held| 100 * held.position.x + held.position.y)
+            .sum()
     }
 }
 
-pub fn part1(data: &str) -> i64 {
-    let mut game = parse(data);
+pub fn warehouse(data: &str, width: usize) -> (Warehouse, Vec<Move>) {
+    let game = parse(data);
 
-    let mut position = game.robot;
+    (Warehouse::new(&game.grid, width), game.moves)
+}
 
-    for mv in &game.moves {
-        if can_move_robot(&position, &game.tile_map, &mv) {
-            move_robot(&position, &mut game.tile_map, &mv);
-            position = &position + mv.advance_by();
-        }
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum Command {
+    Move(Move),
+    Undo,
+    Quit,
+}
+
+// arrow keys arrive as ESC [ A..D since stdin is line buffered
+fn parse_commands(line: &str) -> Vec<Command> {
+    let mut commands = Vec::new();
+    let mut chars = line.chars();
+
+    while let Some(ch) = chars.next() {
+        let command = match ch {
+            'w' | 'W' | '^' => Command::Move(Move::Up),
+            'a' | 'A' | '<' => Command::Move(Move::Left),
+            's' | 'S' | 'v' => Command::Move(Move::Down),
+            'd' | 'D' | '>' => Command::Move(Move::Right),
+            'u' | 'U' => Command::Undo,
+            'q' | 'Q' => Command::Quit,
+            '\x1b' if chars.next() == Some('[') => match chars.next() {
+                Some('A') => Command::Move(Move::Up),
+                Some('B') => Command::Move(Move::Down),
+                Some('C') => Command::Move(Move::Right),
+                Some('D') => Command::Move(Move::Left),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        commands.push(command);
     }
 
-    game.tile_map
-        .iter()
-        .enumerate()
-        .map(|(x, row)| {
-            let sum: i64 = row
-                .iter()
-                .enumerate()
-                .map(|(y, tile)| {
-                    let result: i64 = match tile {
-                        Tile::Box => (100usize * x + y) as i64,
-                        _ => 0i64,
-                    };
-
-                    result
-                })
-                .sum();
-
-            sum
-        })
-        .sum()
+    commands
 }
 
-fn expand_tilemap(tile_map: &Vec<Vec<Tile>>) -> Vec<Vec<Tile>> {
-    let mut result: Vec<Vec<Tile>> = Vec::new();
+fn show<W: Write>(warehouse: &Warehouse, output: &mut W) {
+    writeln!(
+        output,
+        "{}\nmoves: {} gps: {}",
+        warehouse.render(),
+        warehouse.history().len(),
+        warehouse.gps()
+    )
+    .expect("Failed to write frame");
+}
 
-    for row in tile_map {
-        let mut new_row = Vec::new();
-        for tile in row {
-            match tile {
-                Tile::Wall => {
-                    new_row.push(Tile::Wall);
-                    new_row.push(Tile::Wall);
-                }
-                Tile::Empty => {
-                    new_row.push(Tile::Empty);
-                    new_row.push(Tile::Empty);
-                }
-                Tile::Box => {
-                    new_row.push(Tile::BoxLeft);
-                    new_row.push(Tile::BoxRight);
+pub fn play<R: BufRead, W: Write>(warehouse: &mut Warehouse, input: R, output: &mut W) {
+    writeln!(output, "wasd/arrows to move, u to undo, q to quit").expect("Failed to write");
+    show(warehouse, output);
+
+    for line in input.lines() {
+        let line = line.expect("Failed to read input");
+
+        for command in parse_commands(&line) {
+            match command {
+                Command::Move(mv) => {
+                    warehouse.step(mv);
                 }
-                Tile::Robot => {
-                    new_row.push(Tile::Robot);
-                    new_row.push(Tile::Empty);
+                Command::Undo => {
+                    warehouse.undo();
                 }
-                _ => panic!("Bad tile found..."),
+                Command::Quit => return,
             }
         }
-        result.push(new_row);
+
+        show(warehouse, output);
     }
-    result
 }
 
-pub fn part2(data: &str) -> i64 {
-    let mut game = parse(data);
-    game.tile_map = expand_tilemap(&game.tile_map);
-
-    let mut position = Position {
-        x: game.robot.x,
-        y: game.robot.y * 2,
-    };
-
-    for mv in &game.moves {
-        if can_move_robot(&position, &game.tile_map, &mv) {
-            move_robot(&position, &mut game.tile_map, &mv);
-            position = &position + mv.advance_by();
-        }
+pub fn part1(data: &str) -> i64 {
+    let (mut warehouse, moves) = warehouse(data, 1);
+
+    for mv in moves {
+        warehouse.step(mv);
     }
 
-    game.tile_map
-        .iter()
-        .enumerate()
-        .map(|(x, row)| {
-            let sum: i64 = row
-                .iter()
-                .enumerate()
-                .map(|(y, tile)| {
-                    let result: i64 = match tile {
-                        Tile::BoxLeft => {
-                            let x = x as i64;
-                            let y = y as i64;
-                            let x_coord = x;
-                            let y_coord = y;
-
-                            100 * x_coord + y_coord
-                        }
+    warehouse.gps()
+}
 
-                        _ => 0i64,
-                    };
+pub fn part2(data: &str) -> i64 {
+    let (mut warehouse, moves) = warehouse(data, 2);
 
-                    result
-                })
-                .sum();
+    for mv in moves {
+        warehouse.step(mv);
+    }
 
-            sum
-        })
-        .sum()
+    warehouse.gps()
 }
 
 #[cfg(test)]
@@ -395,4 +461,147 @@ vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
 v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
         assert_eq!(part2(data), 9021);
     }
+
+    #[test]
+    fn warehouse_history_works() {
+        let data = "########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########
+
+<^^>>>vv<v>>v<<";
+        let (mut warehouse, moves) = warehouse(data, 1);
+        let initial = warehouse.render();
+
+        assert_eq!(
+            warehouse.first_divergence(
+                &moves,
+                &[
+                    "########
+#..O.O.#
+##@.O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########",
+                    "########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########",
+                    "########
+#.@O.O.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########",
+                    "########
+#..@OO.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########",
+                    "########
+#..@OO.#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########"
+                ]
+            ),
+            Some(4)
+        );
+
+        for mv in moves {
+            warehouse.step(mv);
+        }
+        assert_eq!(warehouse.gps(), 2028);
+        assert!(warehouse.history()[0].blocked);
+        assert_eq!(warehouse.history()[3].moved.len(), 1);
+
+        // the fifth move is where the expected frames went wrong
+        assert_eq!(
+            warehouse.frame(4),
+            "########
+#...@OO#
+##..O..#
+#...O..#
+#.#.O..#
+#...O..#
+#......#
+########"
+        );
+
+        let frame = warehouse.frame(2);
+        while warehouse.history().len() > 3 {
+            warehouse.undo();
+        }
+        assert_eq!(frame, warehouse.render());
+
+        while warehouse.undo().is_some() {}
+        assert_eq!(warehouse.render(), initial);
+    }
+
+    #[test]
+    fn wide_crates_work() {
+        let data = "#######
+#.....#
+#.OO..#
+#..@..#
+#.....#
+#######
+
+^<";
+        let (mut warehouse, moves) = warehouse(data, 3);
+        for mv in moves {
+            warehouse.step(mv);
+        }
+
+        assert_eq!(warehouse.history()[0].moved, vec![1]);
+        assert_eq!(warehouse.history()[1].moved, vec![0]);
+        assert_eq!(warehouse.gps(), 205 + 109);
+        assert_eq!(
+            warehouse.render(),
+            "#####################
+###......[=]......###
+###..[=]@.........###
+###...............###
+###...............###
+#####################"
+        );
+    }
+
+    #[test]
+    fn play_works() {
+        let data = "#####
+#@O.#
+#####";
+        let (mut warehouse, _) = warehouse(data, 1);
+        let mut output = Vec::new();
+
+        play(
+            &mut warehouse,
+            "d\nd\nu\n\x1b[Cq\nd\n".as_bytes(),
+            &mut output,
+        );
+
+        let output = String::from_utf8(output).unwrap();
+        // the second d bumps into the wall, so that's all u takes back
+        assert!(output.ends_with("#####\n#.@O#\n#####\nmoves: 1 gps: 103\n"));
+        assert_eq!(warehouse.render(), "#####\n#.@O#\n#####");
+    }
 }
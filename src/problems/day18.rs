@@ -1,8 +1,9 @@
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, VecDeque},
 };
 
+use aoc2024::components::{Connectivity, IncrementalLabeller};
 use aoc2024::{Direction, Position};
 
 fn parse(data: &str) -> Vec<Position> {
@@ -113,70 +114,188 @@ fn dijkstra(map: &Vec<Vec<bool>>, start: Position, end: Position) -> Option<i64>
     None
 }
 
-pub fn part1(data: &str) -> i64 {
-    let incoming = parse(data);
-
-    let min_x = incoming.iter().map(|p| p.x).min().unwrap();
-    let max_x = incoming.iter().map(|p| p.x).max().unwrap();
-    let min_y = incoming.iter().map(|p| p.y).min().unwrap();
-    let max_y = incoming.iter().map(|p| p.y).max().unwrap();
-
-    let mut tile_map =
-        vec![vec![false; (max_y - min_y) as usize + 1]; (max_x - min_x) as usize + 1];
+const MEMORY_SIZE: Position = Position { x: 71, y: 71 };
+const FALLEN_BYTES: usize = 1024;
 
-    let mut max_bytes = 1024;
-    if incoming.len() < 1024 {
-        max_bytes = 12;
+fn exit(size: &Position) -> Position {
+    Position {
+        x: size.x - 1,
+        y: size.y - 1,
     }
-    for position in &incoming[..max_bytes] {
+}
+
+fn corrupted(incoming: &[Position], size: &Position, bytes: usize) -> Vec<Vec<bool>> {
+    let mut tile_map = vec![vec![false; size.y as usize]; size.x as usize];
+    for position in incoming.iter().take(bytes) {
         tile_map[position.x as usize][position.y as usize] = true;
     }
 
+    tile_map
+}
+
+pub fn shortest_path(data: &str, size: &Position, bytes: usize) -> Option<i64> {
+    let incoming = parse(data);
+    let tile_map = corrupted(&incoming, size, bytes);
     let start = Position { x: 0, y: 0 };
-    let end = Position {
-        x: (max_x - min_x),
-        y: (max_y - min_y),
-    };
 
-    let Some(result) = dijkstra(&tile_map, start, end) else {
-        panic!("No result found!");
-    };
+    // a byte on the start is as blocking as one on the exit
+    if at(&start, &tile_map) {
+        return None;
+    }
 
-    result
+    dijkstra(&tile_map, start, exit(size))
 }
 
-pub fn part2(data: &str) -> String {
+// starts from every byte having fallen and lifts them again in reverse order,
+// joining free cells with a union-find. The byte whose removal first connects
+// the start and exit is the first one to cut them off
+pub fn blocking_byte(data: &str, size: &Position) -> Option<Position> {
     let incoming = parse(data);
+    let (start, end) = (Position { x: 0, y: 0 }, exit(size));
+    let (height, width) = (size.x as usize, size.y as usize);
 
-    let min_x = incoming.iter().map(|p| p.x).min().unwrap();
-    let max_x = incoming.iter().map(|p| p.x).max().unwrap();
-    let min_y = incoming.iter().map(|p| p.y).min().unwrap();
-    let max_y = incoming.iter().map(|p| p.y).max().unwrap();
+    // a byte landing on an already corrupted cell changes nothing
+    let mut first_fall = vec![vec![usize::MAX; width]; height];
+    for (i, position) in incoming.iter().enumerate().rev() {
+        first_fall[position.x as usize][position.y as usize] = i;
+    }
 
-    let mut tile_map =
-        vec![vec![false; (max_y - min_y) as usize + 1]; (max_x - min_x) as usize + 1];
+    let mut labeller = IncrementalLabeller::new(height, width, Connectivity::Four);
+    for x in 0..size.x {
+        for y in 0..size.y {
+            if first_fall[x as usize][y as usize] == usize::MAX {
+                labeller.add(&Position { x, y });
+            }
+        }
+    }
 
-    let start = Position { x: 0, y: 0 };
-    let end = Position {
-        x: (max_x - min_x),
-        y: (max_y - min_y),
-    };
+    if labeller.connected(&start, &end) {
+        return None;
+    }
 
-    let mut result: Option<Position> = None;
+    for (i, position) in incoming.iter().enumerate().rev() {
+        if first_fall[position.x as usize][position.y as usize] != i {
+            continue;
+        }
 
-    for position in incoming.iter() {
-        tile_map[position.x as usize][position.y as usize] = true;
+        labeller.add(position);
+        if labeller.connected(&start, &end) {
+            return Some(*position);
+        }
+    }
+
+    None
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub fallen: usize,
+    pub distance: Option<i64>,
+    pub path: Vec<Position>,
+}
+
+// breadth first with a parent per cell so the path itself can be walked
+// back from the exit
+fn find_path(map: &Vec<Vec<bool>>, start: Position, end: Position) -> Option<Vec<Position>> {
+    if at(&start, map) || at(&end, map) {
+        return None;
+    }
+
+    let mut parent: HashMap<Position, Position> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    parent.insert(start, start);
+
+    while let Some(position) = queue.pop_front() {
+        if position == end {
+            let mut path = vec![end];
+            while *path.last().unwrap() != start {
+                path.push(parent[path.last().unwrap()]);
+            }
+            path.reverse();
+            return Some(path);
+        }
 
-        match dijkstra(&tile_map, start, end) {
-            None => {
-                result = Some(*position);
-                break;
+        for direction in Direction::iterator() {
+            let next = position + direction.advance_by();
+            if !in_bounds(&next, map) || at(&next, map) || parent.contains_key(&next) {
+                continue;
             }
-            Some(_) => {}
+
+            parent.insert(next, position);
+            queue.push_back(next);
         }
     }
 
-    match result {
+    None
+}
+
+// distances only ever grow as bytes fall, so the current path stays a
+// shortest one until a byte lands on it and only then is it searched again.
+// One route per change in distance, `fallen` being how many bytes it took,
+// ending with a route of None once the exit is cut off
+pub fn routes(data: &str, size: &Position) -> Vec<Route> {
+    let incoming = parse(data);
+    let mut tile_map = corrupted(&incoming, size, 0);
+    let (start, end) = (Position { x: 0, y: 0 }, exit(size));
+
+    let mut path = find_path(&tile_map, start, end);
+    let distance = |path: &Option<Vec<Position>>| path.as_ref().map(|path| path.len() as i64 - 1);
+    let mut routes = vec![Route {
+        fallen: 0,
+        distance: distance(&path),
+        path: path.clone().unwrap_or_default(),
+    }];
+
+    for (i, byte) in incoming.iter().enumerate() {
+        tile_map[byte.x as usize][byte.y as usize] = true;
+
+        let Some(current) = &path else {
+            break;
+        };
+        if !current.contains(byte) {
+            continue;
+        }
+
+        let next = find_path(&tile_map, start, end);
+        if distance(&next) != distance(&path) {
+            routes.push(Route {
+                fallen: i + 1,
+                distance: distance(&next),
+                path: next.clone().unwrap_or_default(),
+            });
+        }
+        path = next;
+    }
+
+    routes
+}
+
+// the shortest distance after each number of fallen bytes, from none to all
+pub fn distances(data: &str, size: &Position) -> Vec<Option<i64>> {
+    let bytes = parse(data).len();
+    let routes = routes(data, size);
+
+    (0..=bytes)
+        .map(|fallen| {
+            routes
+                .iter()
+                .rev()
+                .find(|route| route.fallen <= fallen)
+                .and_then(|route| route.distance)
+        })
+        .collect()
+}
+
+pub fn part1(data: &str) -> i64 {
+    let Some(result) = shortest_path(data, &MEMORY_SIZE, FALLEN_BYTES) else {
+        panic!("No result found!");
+    };
+
+    result
+}
+
+pub fn part2(data: &str) -> String {
+    match blocking_byte(data, &MEMORY_SIZE) {
         Some(pos) => format!("{},{}", pos.x, pos.y),
         None => panic!("FAILED to find byteing-point"),
     }
@@ -213,7 +332,7 @@ mod tests {
 0,5
 1,6
 2,0";
-        assert_eq!(part1(data), 22);
+        assert_eq!(shortest_path(data, &Position { x: 7, y: 7 }, 12), Some(22));
     }
 
     #[test]
@@ -243,6 +362,58 @@ mod tests {
 0,5
 1,6
 2,0";
-        assert_eq!(part2(data), "6,1");
+        assert_eq!(
+            blocking_byte(data, &Position { x: 7, y: 7 }),
+            Some(Position { x: 6, y: 1 })
+        );
+    }
+
+    #[test]
+    fn routes_work() {
+        let data = "5,4
+4,2
+4,5
+3,0
+2,1
+6,3
+2,4
+1,5
+0,6
+3,3
+2,6
+5,1
+1,2
+5,5
+2,5
+6,5
+1,4
+0,4
+6,4
+1,1
+6,1
+1,0
+0,5
+1,6
+2,0";
+        let size = Position { x: 7, y: 7 };
+        let distances = distances(data, &size);
+        for (fallen, distance) in distances.iter().enumerate() {
+            assert_eq!(*distance, shortest_path(data, &size, fallen));
+        }
+
+        let routes = routes(data, &size);
+        assert_eq!(routes.first().unwrap().distance, Some(12));
+        assert_eq!(routes.last().unwrap().fallen, 21);
+        assert_eq!(routes.last().unwrap().distance, None);
+
+        for route in routes.iter().filter(|route| route.distance.is_some()) {
+            let tile_map = corrupted(&parse(data), &size, route.fallen);
+            assert_eq!(route.path.len() as i64 - 1, route.distance.unwrap());
+            assert!(route.path.iter().all(|position| !at(position, &tile_map)));
+            assert!(route
+                .path
+                .windows(2)
+                .all(|pair| (pair[0].x - pair[1].x).abs() + (pair[0].y - pair[1].y).abs() == 1));
+        }
     }
 }
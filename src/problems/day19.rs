@@ -1,110 +1,306 @@
-use std::collections::HashMap;
-
-#[derive(PartialEq, Eq, Hash, Debug, Ord, PartialOrd)]
-enum Stripe {
-    White = 0,
-    Blue,
-    Black,
-    Red,
-    Green,
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Ord, PartialOrd)]
+struct Stripe(usize);
+
+// the colours a towel can have, each stripe being an index into them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alphabet {
+    colours: Vec<char>,
 }
 
-impl Stripe {
-    fn from_char(ch: char) -> Stripe {
-        match ch {
-            'w' => Stripe::White,
-            'u' => Stripe::Blue,
-            'b' => Stripe::Black,
-            'r' => Stripe::Red,
-            'g' => Stripe::Green,
-            _ => panic!("Unexpected stripe colour {ch}"),
-        }
+impl Alphabet {
+    fn new(colours: impl Iterator<Item = char>) -> Alphabet {
+        let mut colours: Vec<char> = colours.collect();
+        colours.sort();
+        colours.dedup();
+        Alphabet { colours }
+    }
+
+    pub fn colours(&self) -> &[char] {
+        &self.colours
+    }
+
+    fn stripe(&self, ch: char) -> Option<Stripe> {
+        self.colours.binary_search(&ch).ok().map(Stripe)
+    }
+
+    fn colour(&self, stripe: Stripe) -> char {
+        self.colours[stripe.0]
     }
 }
 
-fn parse(data: &str) -> (Vec<Vec<Stripe>>, Vec<Vec<Stripe>>) {
-    let (towels, designs) = data.trim().split_once("\n\n").unwrap();
+// an optional first line of `colours=wubrg` declares the alphabet, otherwise
+// it's every colour that shows up in the towels or designs
+fn parse(data: &str) -> (Alphabet, Vec<Vec<Stripe>>, Vec<String>) {
+    let mut data = data.trim();
+    let mut declared = None;
+    if let Some(rest) = data.strip_prefix("colours=") {
+        let (colours, rest) = rest.split_once("\n").unwrap();
+        declared = Some(Alphabet::new(colours.trim().chars()));
+        data = rest;
+    }
 
-    let mut towels: Vec<Vec<Stripe>> = towels
+    let (towels, designs) = data.split_once("\n\n").unwrap();
+    let alphabet = declared.unwrap_or_else(|| {
+        Alphabet::new(data.chars().filter(|ch| !ch.is_whitespace() && *ch != ','))
+    });
+
+    let towels: Vec<Vec<Stripe>> = towels
         .split(", ")
-        .map(|v| v.chars().map(|v| Stripe::from_char(v)).collect())
+        .map(|towel| {
+            let towel = towel.trim();
+            towel
+                .chars()
+                .map(|ch| {
+                    alphabet
+                        .stripe(ch)
+                        .unwrap_or_else(|| panic!("Towel {towel} uses undeclared colour {ch}"))
+                })
+                .collect()
+        })
         .collect();
 
-    towels.sort();
+    let designs: Vec<String> = designs.split("\n").map(|v| v.to_string()).collect();
 
-    let designs: Vec<Vec<Stripe>> = designs
-        .split("\n")
-        .map(|v| v.chars().map(|v| Stripe::from_char(v)).collect())
-        .collect();
+    (alphabet, towels, designs)
+}
+
+struct Node {
+    children: Vec<Option<usize>>,
+    towel: Option<usize>,
+}
+
+impl Node {
+    fn new(colours: usize) -> Node {
+        Node {
+            children: vec![None; colours],
+            towel: None,
+        }
+    }
+}
 
-    (towels, designs)
+// every towel spelled out from the root, a node marking where one ends
+struct Trie {
+    nodes: Vec<Node>,
 }
 
-fn is_possible(towels: &Vec<Vec<Stripe>>, design: &[Stripe]) -> bool {
-    if design.len() == 0 {
-        return true;
+impl Trie {
+    fn new(towels: &[Vec<Stripe>], colours: usize) -> Trie {
+        let mut trie = Trie {
+            nodes: vec![Node::new(colours)],
+        };
+
+        for (i, towel) in towels.iter().enumerate() {
+            let mut node = 0;
+            for &stripe in towel {
+                node = match trie.nodes[node].children[stripe.0] {
+                    Some(child) => child,
+                    None => {
+                        trie.nodes.push(Node::new(colours));
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children[stripe.0] = Some(child);
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].towel = Some(i);
+        }
+
+        trie
     }
 
-    for towel in towels {
-        if design.starts_with(towel) && is_possible(towels, &design[towel.len()..]) {
-            return true;
+    // (length, towel) for each towel the design starts with, shortest first
+    fn prefixes(&self, design: &[Stripe]) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        let mut node = 0;
+
+        for (i, &stripe) in design.iter().enumerate() {
+            let Some(child) = self.nodes[node].children[stripe.0] else {
+                break;
+            };
+            node = child;
+
+            if let Some(towel) = self.nodes[node].towel {
+                result.push((i + 1, towel));
+            }
         }
+
+        result
     }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DesignReport {
+    pub design: String,
+    pub possible: bool,
+    pub arrangements: u64,
+    pub fewest: Option<usize>,
+}
 
-    false
+pub struct Towels {
+    alphabet: Alphabet,
+    towels: Vec<Vec<Stripe>>,
+    trie: Trie,
 }
 
-fn solve<'a>(
-    towels: &Vec<Vec<Stripe>>,
-    design: &'a [Stripe],
-    i: usize,
-    cache: &mut HashMap<(usize, &'a [Stripe]), i64>,
-) -> i64 {
-    if cache.contains_key(&(i, design)) {
-        return cache[&(i, design)];
+impl Towels {
+    fn new(alphabet: Alphabet, towels: Vec<Vec<Stripe>>) -> Towels {
+        let trie = Trie::new(&towels, alphabet.colours.len());
+        Towels {
+            alphabet,
+            towels,
+            trie,
+        }
+    }
+
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    // the number of ways to make each suffix of the design, the empty one
+    // at the end being the one way to make nothing
+    fn suffix_ways(&self, design: &[Stripe]) -> Vec<u64> {
+        let mut ways = vec![0u64; design.len() + 1];
+        ways[design.len()] = 1;
+
+        for i in (0..design.len()).rev() {
+            ways[i] = self
+                .trie
+                .prefixes(&design[i..])
+                .iter()
+                .fold(0, |total: u64, (length, _)| {
+                    total.saturating_add(ways[i + length])
+                });
+        }
+
+        ways
+    }
+
+    // None if the design has a colour outside the alphabet, which no towel
+    // can ever match
+    fn stripes(&self, design: &str) -> Option<Vec<Stripe>> {
+        design.chars().map(|ch| self.alphabet.stripe(ch)).collect()
+    }
+
+    pub fn count(&self, design: &str) -> u64 {
+        match self.stripes(design) {
+            Some(design) => self.suffix_ways(&design)[0],
+            None => 0,
+        }
+    }
+
+    pub fn is_possible(&self, design: &str) -> bool {
+        self.count(design) > 0
     }
 
-    if design.len() > 0 && i >= towels.len() {
-        cache.insert((i, design), 0);
-        return 0;
+    // same suffix walk as the count but keeping the shortest way to finish
+    pub fn fewest(&self, design: &str) -> Option<usize> {
+        let design = self.stripes(design)?;
+        let mut fewest: Vec<Option<usize>> = vec![None; design.len() + 1];
+        fewest[design.len()] = Some(0);
+
+        for i in (0..design.len()).rev() {
+            fewest[i] = self
+                .trie
+                .prefixes(&design[i..])
+                .iter()
+                .filter_map(|(length, _)| fewest[i + length])
+                .min()
+                .map(|towels| towels + 1);
+        }
+
+        fewest[0]
     }
 
-    if design.len() == 0 {
-        cache.insert((i, design), 1);
-        return 1;
+    pub fn report(&self, design: &str) -> DesignReport {
+        let fewest = self.fewest(design);
+        DesignReport {
+            design: design.to_string(),
+            possible: fewest.is_some(),
+            arrangements: self.count(design),
+            fewest,
+        }
     }
 
-    let mut result: i64 = 0;
-    let towel = &towels[i];
-    if i < towels.len() && design.starts_with(towel) {
-        result += solve(towels, &design[towel.len()..], 0, cache);
+    fn enumerate(
+        &self,
+        design: &[Stripe],
+        from: usize,
+        ways: &[u64],
+        current: &mut Vec<usize>,
+        result: &mut Vec<Vec<usize>>,
+        limit: usize,
+    ) {
+        if from == design.len() {
+            result.push(current.clone());
+            return;
+        }
+
+        for (length, towel) in self.trie.prefixes(&design[from..]) {
+            if result.len() >= limit {
+                return;
+            }
+            // no point going down a suffix that can't be finished
+            if ways[from + length] == 0 {
+                continue;
+            }
+
+            current.push(towel);
+            self.enumerate(design, from + length, ways, current, result, limit);
+            current.pop();
+        }
     }
 
-    result += solve(towels, design, i + 1, cache);
-    cache.insert((i, design), result);
-    result
+    // up to `limit` arrangements as the towels used, in the order they're
+    // laid down and with shorter towels tried first
+    pub fn first_arrangements(&self, design: &str, limit: usize) -> Vec<Vec<String>> {
+        let Some(design) = self.stripes(design) else {
+            return Vec::new();
+        };
+        let ways = self.suffix_ways(&design);
+
+        let mut result = Vec::new();
+        if limit > 0 {
+            self.enumerate(&design, 0, &ways, &mut Vec::new(), &mut result, limit);
+        }
+
+        result
+            .iter()
+            .map(|arrangement| {
+                arrangement
+                    .iter()
+                    .map(|&towel| {
+                        self.towels[towel]
+                            .iter()
+                            .map(|&stripe| self.alphabet.colour(stripe))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+pub fn towels(data: &str) -> (Towels, Vec<String>) {
+    let (alphabet, towels, designs) = parse(data);
+    (Towels::new(alphabet, towels), designs)
 }
 
 pub fn part1(data: &str) -> i64 {
-    let (towels, designs) = parse(data);
+    let (towels, designs) = towels(data);
 
-    let mut count = 0;
-    for design in designs {
-        count += is_possible(&towels, &design) as i64;
-    }
-    count
+    designs
+        .iter()
+        .filter(|design| towels.is_possible(design))
+        .count() as i64
 }
 
 pub fn part2(data: &str) -> i64 {
-    let (towels, designs) = parse(data);
+    let (towels, designs) = towels(data);
 
-    let mut cache = HashMap::new();
-    let mut count = 0;
-    for design in &designs {
-        let result = solve(&towels, &design, 0, &mut cache);
-        count += result;
-    }
-    count
+    designs
+        .iter()
+        .map(|design| towels.count(design) as i64)
+        .sum()
 }
 
 #[cfg(test)]
@@ -142,4 +338,74 @@ bbrgwb
 ";
         assert_eq!(part2(data), 16);
     }
+
+    #[test]
+    fn arrangements_work() {
+        let data = "r, wr, b, g, bwu, rb, gb, br
+
+brwrr
+bggr
+gbbr
+rrbgbr
+ubwu
+bwurrg
+brgr
+bbrgwb
+";
+        let (towels, designs) = towels(data);
+        let counts: Vec<u64> = designs.iter().map(|design| towels.count(design)).collect();
+        assert_eq!(counts, vec![2, 1, 4, 6, 0, 1, 2, 0]);
+
+        assert_eq!(
+            towels.first_arrangements("gbbr", 3),
+            vec![
+                vec!["g", "b", "b", "r"],
+                vec!["g", "b", "br"],
+                vec!["gb", "b", "r"],
+            ]
+        );
+        assert!(towels.first_arrangements("ubwu", 3).is_empty());
+    }
+
+    #[test]
+    fn other_alphabets_work() {
+        let data = "colours=xyz
+x, xy, yz, zzz
+
+xyzzz
+xyzx
+zz
+";
+        let (towels, designs) = towels(data);
+        assert_eq!(towels.alphabet().colours(), &['x', 'y', 'z']);
+
+        let reports: Vec<(bool, u64, Option<usize>)> = designs
+            .iter()
+            .map(|design| towels.report(design))
+            .map(|report| (report.possible, report.arrangements, report.fewest))
+            .collect();
+        assert_eq!(
+            reports,
+            vec![(true, 1, Some(2)), (true, 1, Some(3)), (false, 0, None)]
+        );
+
+        // a colour outside the declared ones can never be made
+        let report = towels.report("xyq");
+        assert!(!report.possible);
+        assert_eq!(report.arrangements, 0);
+        assert_eq!(report.fewest, None);
+        assert!(towels.first_arrangements("xyq", 3).is_empty());
+
+        // inferred from the towels and designs instead
+        let (towels, designs) = super::towels("r, wr, b, g, bwu, rb, gb, br\n\nbrwrr\nbggr");
+        assert_eq!(towels.alphabet().colours(), &['b', 'g', 'r', 'u', 'w']);
+        assert_eq!(towels.fewest(&designs[0]), Some(3));
+        assert_eq!(towels.fewest(&designs[1]), Some(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Towel xq uses undeclared colour q")]
+    fn undeclared_towel_colour_panics() {
+        towels("colours=xyz\nx, xq\n\nxx");
+    }
 }
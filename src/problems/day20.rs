@@ -1,6 +1,6 @@
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{BTreeMap, BinaryHeap, HashMap},
 };
 
 use aoc2024::{Direction, Position};
@@ -157,7 +157,99 @@ fn taxicab(from: &Position, to: &Position) -> i64 {
     (from.x - to.x).abs() + (from.y - to.y).abs()
 }
 
-fn count_cost_savings_for_cheats(map: &Vec<Vec<char>>, threshold: i64, cheat_length: i64) -> i64 {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub start: Position,
+    pub end: Position,
+    pub saving: i64,
+}
+
+// the track as a list of cells from S to E if it's a single corridor with no
+// branches or dead ends, which is what the puzzle inputs are
+fn corridor(map: &Vec<Vec<char>>) -> Option<Vec<Position>> {
+    let start = find('S', map)?;
+    let end = find('E', map)?;
+    let open = map.iter().flatten().filter(|&&ch| ch != '#').count();
+
+    let mut path = vec![start];
+    let mut previous: Option<Position> = None;
+    while *path.last().unwrap() != end && path.len() <= open {
+        let current = *path.last().unwrap();
+        let next: Vec<Position> = Direction::iterator()
+            .map(|direction| current + direction.advance_by())
+            .filter(|next| in_bounds(next, map) && at(next, map) != '#')
+            .filter(|next| Some(*next) != previous)
+            .collect();
+
+        if next.len() != 1 {
+            return None;
+        }
+
+        previous = Some(current);
+        path.push(next[0]);
+    }
+
+    // anything left over is a branch off the side of the corridor
+    (path.len() == open).then_some(path)
+}
+
+// with the track laid out in order the cost from S is the index and the cost
+// to E is what's left, so a cheat between two cells saves the difference in
+// index less the distance jumped. Each cell only looks at the offsets within
+// the cheat radius through a grid of indices
+fn corridor_cheats(
+    map: &Vec<Vec<char>>,
+    path: &[Position],
+    threshold: i64,
+    cheat_length: i64,
+) -> Vec<Cheat> {
+    let mut index = vec![vec![None; map[0].len()]; map.len()];
+    for (i, position) in path.iter().enumerate() {
+        index[position.x as usize][position.y as usize] = Some(i as i64);
+    }
+
+    let offsets: Vec<Position> = (-cheat_length..=cheat_length)
+        .flat_map(|x| (-cheat_length..=cheat_length).map(move |y| Position { x, y }))
+        .filter(|offset| (2..=cheat_length).contains(&(offset.x.abs() + offset.y.abs())))
+        .collect();
+
+    let mut cheats = Vec::new();
+    for (i, &position) in path.iter().enumerate() {
+        // the general method never starts a cheat on the edge of the map
+        if position.x == 0
+            || position.y == 0
+            || position.x == map.len() as i64 - 1
+            || position.y == map[0].len() as i64 - 1
+        {
+            continue;
+        }
+
+        for offset in offsets.iter() {
+            let jump_position = position + *offset;
+            if !in_bounds(&jump_position, map) {
+                continue;
+            }
+            let Some(j) = index[jump_position.x as usize][jump_position.y as usize] else {
+                continue;
+            };
+
+            let saving = j - i as i64 - (offset.x.abs() + offset.y.abs());
+            if saving >= threshold {
+                cheats.push(Cheat {
+                    start: position,
+                    end: jump_position,
+                    saving,
+                });
+            }
+        }
+    }
+
+    cheats
+}
+
+// every cheat of up to `cheat_length` picoseconds that saves at least
+// `threshold`, from the costs of getting to each cell from either end
+fn general_cheats(map: &Vec<Vec<char>>, threshold: i64, cheat_length: i64) -> Vec<Cheat> {
     let start = find('S', &map).unwrap();
     let end = find('E', &map).unwrap();
 
@@ -167,7 +259,7 @@ fn count_cost_savings_for_cheats(map: &Vec<Vec<char>>, threshold: i64, cheat_len
 
     let (_, end_to_start_costs) = dijkstra(&map, &end, &start).unwrap();
 
-    let mut saving_count = 0;
+    let mut cheats = Vec::new();
 
     for x in 1..map.len() - 1 {
         for y in 1..map[0].len() - 1 {
@@ -187,28 +279,67 @@ fn count_cost_savings_for_cheats(map: &Vec<Vec<char>>, threshold: i64, cheat_len
                     let saving = cost - cost_with_cheat;
 
                     if saving >= threshold {
-                        saving_count += 1;
+                        cheats.push(Cheat {
+                            start: position,
+                            end: *jump_position,
+                            saving,
+                        });
                     }
                 }
             }
         }
     }
 
-    saving_count
+    cheats
 }
 
-pub fn part1(data: &str) -> i64 {
-    let map = parse(data);
+fn find_cheats(map: &Vec<Vec<char>>, threshold: i64, cheat_length: i64) -> Vec<Cheat> {
+    match corridor(map) {
+        Some(path) => corridor_cheats(map, &path, threshold, cheat_length),
+        None => general_cheats(map, threshold, cheat_length),
+    }
+}
 
-    count_cost_savings_for_cheats(&map, if cfg!(test) { 64 } else { 100 }, 2)
+pub fn count_cheats(data: &str, threshold: i64, cheat_length: i64) -> i64 {
+    find_cheats(&parse(data), threshold, cheat_length).len() as i64
 }
 
-pub fn part2(data: &str) -> i64 {
-    let map = parse(data);
+// how many cheats save each amount, as in the table in the puzzle text
+pub fn savings_histogram(data: &str, threshold: i64, cheat_length: i64) -> BTreeMap<i64, usize> {
+    let mut histogram = BTreeMap::new();
+    for cheat in find_cheats(&parse(data), threshold, cheat_length) {
+        *histogram.entry(cheat.saving).or_default() += 1;
+    }
 
+    histogram
+}
+
+// biggest savings first, ties in the order of where the cheat starts and ends
+pub fn list_cheats(
+    data: &str,
+    threshold: i64,
+    cheat_length: i64,
+    limit: Option<usize>,
+) -> Vec<Cheat> {
+    let mut cheats = find_cheats(&parse(data), threshold, cheat_length);
+    cheats.sort_by(|a, b| {
+        b.saving
+            .cmp(&a.saving)
+            .then_with(|| (a.start, a.end).cmp(&(b.start, b.end)))
+    });
+    cheats.truncate(limit.unwrap_or(cheats.len()));
+
+    cheats
+}
+
+pub fn part1(data: &str) -> i64 {
+    count_cheats(data, 100, 2)
+}
+
+pub fn part2(data: &str) -> i64 {
     // after a bit of refactorign - took ages to realise that you were allowed
     // to keep cheating even after you reached an empty '.' space.
-    count_cost_savings_for_cheats(&map, if cfg!(test) { 50 } else { 100 }, 20)
+    count_cheats(data, 100, 20)
 }
 
 #[cfg(test)]
@@ -233,7 +364,7 @@ mod tests {
 #...#...#...###
 ###############";
         //1327
-        assert_eq!(part1(data), 1);
+        assert_eq!(count_cheats(data, 64, 2), 1);
     }
 
     #[test]
@@ -254,8 +385,105 @@ mod tests {
 #...#...#...###
 ###############";
         assert_eq!(
-            part2(data),
+            count_cheats(data, 50, 20),
             32 + 31 + 29 + 39 + 25 + 23 + 20 + 19 + 12 + 14 + 12 + 22 + 4 + 3
         );
     }
+
+    #[test]
+    fn savings_histogram_works() {
+        let data = "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############";
+        let histogram = savings_histogram(data, 1, 2);
+        assert_eq!(
+            histogram.into_iter().collect::<Vec<(i64, usize)>>(),
+            vec![
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1)
+            ]
+        );
+
+        let cheats = list_cheats(data, 1, 2, Some(2));
+        assert_eq!(
+            cheats,
+            vec![
+                Cheat {
+                    start: Position { x: 7, y: 7 },
+                    end: Position { x: 7, y: 5 },
+                    saving: 64
+                },
+                Cheat {
+                    start: Position { x: 7, y: 7 },
+                    end: Position { x: 9, y: 7 },
+                    saving: 40
+                }
+            ]
+        );
+        assert_eq!(list_cheats(data, 1, 2, None).len(), 44);
+    }
+
+    #[test]
+    fn corridor_matches_general() {
+        let data = "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############";
+        let map = parse(data);
+        let path = corridor(&map).unwrap();
+        assert_eq!(path.len(), 85);
+
+        let sorted = |mut cheats: Vec<Cheat>| {
+            cheats.sort_by_key(|cheat| (cheat.start, cheat.end));
+            cheats
+        };
+        for (threshold, cheat_length) in [(1, 2), (-100, 2), (0, 6), (50, 20), (-5, 25)] {
+            assert_eq!(
+                sorted(corridor_cheats(&map, &path, threshold, cheat_length)),
+                sorted(general_cheats(&map, threshold, cheat_length))
+            );
+        }
+
+        // a dead end off the side means it isn't a single corridor any more
+        let data = "#########
+#S......#
+#######.#
+#E......#
+####.####
+#########";
+        assert_eq!(corridor(&parse(data)), None);
+        assert_eq!(count_cheats(data, 1, 2), 6);
+    }
 }
@@ -162,99 +162,114 @@ fn arrow_to_arrow(chars: &[char]) -> Vec<char> {
     }
 }
 
-fn shortest_sequence_between_two(
-    start: char,
-    end: char,
-    robots: u64,
-    ocache: &mut HashMap<(char, char, u64), u64>,
-) -> u64 {
-    if ocache.contains_key(&(start, end, robots)) {
-        return *ocache.get(&(start, end, robots)).unwrap();
-    }
-
-    if robots == 0 {
-        return 1;
-    }
-
-    let result = shortest_sequence(arrow_to_arrow(&[start, end]), robots - 1, ocache);
+const ARROWS: [char; 5] = ['A', '^', '<', 'v', '>'];
 
-    ocache.insert((start, end, robots), result);
-
-    result
+#[derive(Debug, PartialEq, Eq)]
+pub struct CodeComplexity {
+    pub code: String,
+    pub length: u128,
+    pub numeric: u64,
+    pub complexity: u128,
 }
 
-fn shortest_sequence(
-    chars: Vec<char>,
-    robots: u64,
-    ocache: &mut HashMap<(char, char, u64), u64>,
-) -> u64 {
-    let mut result: u64 = 0;
+fn sequence_cost(chars: &[char], costs: &HashMap<(char, char), u128>) -> u128 {
+    let mut result: u128 = 0;
     let mut start = 'A';
 
     for &end in chars.iter() {
-        result += shortest_sequence_between_two(start, end, robots, ocache);
+        result = result.saturating_add(costs[&(start, end)]);
         start = end;
     }
 
     result
 }
 
-fn cumulative_shortest_sequence(code: &[char], count_robots: u64) -> u64 {
+// cost of pressing `end` after `start` on the outermost arrow pad, once
+// `robots` robots sit between it and the human. Counts saturate at u128::MAX,
+// and once every entry has saturated the table stops changing so we bail early.
+fn arrow_costs(robots: u64) -> HashMap<(char, char), u128> {
+    let mut costs: HashMap<(char, char), u128> = ARROWS
+        .iter()
+        .flat_map(|&start| ARROWS.iter().map(move |&end| ((start, end), 1)))
+        .collect();
+
+    for _ in 0..robots {
+        let next: HashMap<(char, char), u128> = costs
+            .keys()
+            .map(|&(start, end)| {
+                (
+                    (start, end),
+                    sequence_cost(&arrow_to_arrow(&[start, end]), &costs),
+                )
+            })
+            .collect();
+
+        if next == costs {
+            break;
+        }
+        costs = next;
+    }
+
+    costs
+}
+
+fn cumulative_shortest_sequence(code: &[char], costs: &HashMap<(char, char), u128>) -> u128 {
     let code: Vec<char> = ['A'].iter().cloned().chain(code.iter().cloned()).collect();
     let robot: Vec<char> = code
         .windows(2)
-        .map(|slice| numpad_to_arrow((slice[0], slice[1])))
-        .flatten()
+        .flat_map(|slice| numpad_to_arrow((slice[0], slice[1])))
         .collect();
 
-    let mut ocache = HashMap::new();
-    shortest_sequence(robot, count_robots, &mut ocache)
+    sequence_cost(&robot, costs)
+}
+
+fn numeric_part(code: &[char]) -> u64 {
+    code.iter()
+        .filter(|ch| ch.is_ascii_digit())
+        .fold(0, |acc, ch| acc * 10 + ch.to_digit(10).unwrap() as u64)
 }
 
 fn parse(data: &str) -> Vec<Vec<char>> {
     data.trim()
         .split("\n")
-        .map(|line| line.chars().collect())
+        .map(|line| line.trim().chars().collect())
         .collect()
 }
 
-pub fn part1(data: &str) -> u64 {
+pub fn complexities(data: &str, robots: u64) -> Vec<CodeComplexity> {
     let codes = parse(data);
+    let costs = arrow_costs(robots);
 
-    let sum: u64 = codes
+    codes
         .iter()
         .map(|code| {
-            cumulative_shortest_sequence(&code, 2)
-                * code[..code.len() - 1]
-                    .into_iter()
-                    .collect::<String>()
-                    .parse::<u64>()
-                    .unwrap()
+            let length = cumulative_shortest_sequence(code, &costs);
+            let numeric = numeric_part(code);
+            CodeComplexity {
+                code: code.iter().collect(),
+                length,
+                numeric,
+                complexity: length.saturating_mul(numeric as u128),
+            }
         })
-        .sum();
+        .collect()
+}
+
+pub fn total_complexity(data: &str, robots: u64) -> u128 {
+    complexities(data, robots)
+        .iter()
+        .fold(0, |acc: u128, code| acc.saturating_add(code.complexity))
+}
 
+pub fn part1(data: &str) -> u128 {
     // too high: 164684
     // too high: 161472
     // wrong: 156544
-    sum
+    total_complexity(data, 2)
 }
 
-pub fn part2(data: &str) -> u64 {
-    let codes = parse(data);
-
-    let sum: u64 = codes
-        .iter()
-        .map(|code| {
-            cumulative_shortest_sequence(&code, 25)
-                * code[..code.len() - 1]
-                    .into_iter()
-                    .collect::<String>()
-                    .parse::<u64>()
-                    .unwrap()
-        })
-        .sum();
-
-    sum
+pub fn part2(data: &str) -> u128 {
+    total_complexity(data, 25)
 }
 
 #[cfg(test)]
@@ -280,4 +295,17 @@ mod tests {
 379A";
         assert_eq!(part2(data), 154115708116294);
     }
+
+    #[test]
+    fn complexities_works() {
+        let data = "029A
+980A";
+        let result = complexities(data, 2);
+        assert_eq!(result[0].length, 68);
+        assert_eq!(result[0].numeric, 29);
+        assert_eq!(result[1].complexity, 60 * 980);
+
+        let deep = total_complexity(data, 10_000);
+        assert_eq!(deep, u128::MAX);
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::thread;
 
 use aoc2024::Direction;
 
@@ -9,23 +10,24 @@ fn parse(data: &str) -> Vec<Vec<char>> {
         .collect()
 }
 
-enum TraverseResult {
-    Cycle,
-    Terminated(HashSet<(i32, i32)>),
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Guard {
+    pub x: i32,
+    pub y: i32,
+    pub direction: Direction,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
-struct Guard {
-    x: i32,
-    y: i32,
-    direction: Direction,
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Trajectory {
+    pub steps: Vec<Guard>,
+    pub looped: bool,
 }
 
 fn inbounds(x: i32, y: i32, width: i32, height: i32) -> bool {
     (x >= 0 && x < width) && (y >= 0 && y < height)
 }
 
-fn get_guard(map: &Vec<Vec<char>>) -> Result<Guard, ()> {
+fn get_guard(map: &[Vec<char>]) -> Result<Guard, ()> {
     for (x, row) in map.iter().enumerate() {
         for (y, &value) in row.iter().enumerate() {
             match Direction::from(value) {
@@ -44,99 +46,304 @@ fn get_guard(map: &Vec<Vec<char>>) -> Result<Guard, ()> {
     Err(())
 }
 
-fn traverse(map: &Vec<Vec<char>>) -> TraverseResult {
-    let mut guard = get_guard(&map).unwrap();
+// the map flattened into a grid with, for every cell and direction, the cell
+// the guard ends up on before the next obstacle (None if it walks off the map)
+struct Lab {
+    width: usize,
+    height: usize,
+    obstacles: Vec<bool>,
+    jumps: Vec<Option<usize>>,
+    guard: Guard,
+}
 
-    let mut visited: HashSet<Guard> = HashSet::new();
-    let mut visited_pos: HashSet<(i32, i32)> = HashSet::new();
-    visited.insert(guard.clone());
-    visited_pos.insert((guard.x, guard.y));
+impl Lab {
+    fn new(map: &[Vec<char>]) -> Lab {
+        let height = map.len();
+        let width = map[0].len();
+        let obstacles: Vec<bool> = map.iter().flatten().map(|&ch| ch == '#').collect();
+        let mut jumps = vec![None; width * height * 4];
 
-    while inbounds(guard.x, guard.y, map.len() as i32, map[0].len() as i32) {
-        let (advance_x, advance_y) = guard.direction.advance();
-        let test_guard = Guard {
-            x: guard.x.clone() + advance_x,
-            y: guard.y.clone() + advance_y,
-            direction: guard.direction.clone(),
-        };
+        for &direction in Direction::iterator() {
+            let (dx, dy) = direction.advance();
+            let rows: Vec<usize> = match dx > 0 {
+                true => (0..height).rev().collect(),
+                false => (0..height).collect(),
+            };
+            let cols: Vec<usize> = match dy > 0 {
+                true => (0..width).rev().collect(),
+                false => (0..width).collect(),
+            };
 
-        if visited.contains(&test_guard) {
-            return TraverseResult::Cycle;
-        } else if !inbounds(
-            test_guard.x,
-            test_guard.y,
-            map.len() as i32,
-            map[0].len() as i32,
-        ) {
-            guard = test_guard.clone()
-        } else {
-            match map[test_guard.x as usize][test_guard.y as usize] {
-                '#' => {
-                    guard = Guard {
-                        x: guard.x,
-                        y: guard.y,
-                        direction: guard.direction.turn(),
-                    };
-                }
-                '.' | '^' | 'v' | '>' | '<' => {
-                    guard = Guard {
-                        x: test_guard.x,
-                        y: test_guard.y,
-                        direction: test_guard.direction,
-                    };
-                    visited.insert(guard.clone());
-                    visited_pos.insert((guard.x, guard.y));
+            for &x in rows.iter() {
+                for &y in cols.iter() {
+                    let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                    let cell = x * width + y;
+
+                    jumps[cell * 4 + direction as usize] =
+                        if !inbounds(nx, ny, height as i32, width as i32) {
+                            None
+                        } else {
+                            let next = nx as usize * width + ny as usize;
+                            match obstacles[next] {
+                                true => Some(cell),
+                                false => jumps[next * 4 + direction as usize],
+                            }
+                        };
                 }
-                _ => panic!("Unexpected map item.",),
             }
         }
+
+        Lab {
+            width,
+            height,
+            obstacles,
+            jumps,
+            guard: get_guard(map).unwrap(),
+        }
+    }
+
+    fn cell(&self, x: i32, y: i32) -> usize {
+        x as usize * self.width + y as usize
+    }
+
+    fn blocked(&self, x: i32, y: i32, obstruction: Option<(i32, i32)>) -> bool {
+        self.obstacles[self.cell(x, y)] || obstruction == Some((x, y))
+    }
+
+    // every position and direction the guard takes, one entry per step or
+    // turn, stopping when it walks off the map or repeats a state
+    fn walk(&self, obstruction: Option<(i32, i32)>) -> Trajectory {
+        let mut seen = vec![false; self.width * self.height * 4];
+        let mut guard = self.guard;
+        let mut steps = vec![guard];
+        seen[self.cell(guard.x, guard.y) * 4 + guard.direction as usize] = true;
+
+        loop {
+            let (dx, dy) = guard.direction.advance();
+            let (x, y) = (guard.x + dx, guard.y + dy);
+
+            if !inbounds(x, y, self.height as i32, self.width as i32) {
+                return Trajectory {
+                    steps,
+                    looped: false,
+                };
+            }
+
+            guard = match self.blocked(x, y, obstruction) {
+                true => Guard {
+                    direction: guard.direction.turn(),
+                    ..guard
+                },
+                false => Guard { x, y, ..guard },
+            };
+
+            let state = self.cell(guard.x, guard.y) * 4 + guard.direction as usize;
+            if seen[state] {
+                return Trajectory {
+                    steps,
+                    looped: true,
+                };
+            }
+            seen[state] = true;
+            steps.push(guard);
+        }
+    }
+
+    // jumps between turning points with one extra obstruction in place, the
+    // visited bitmap is stamped with `stamp` so it never needs clearing
+    fn loops_with(
+        &self,
+        obstruction: (i32, i32),
+        from: Guard,
+        visited: &mut [u32],
+        stamp: u32,
+    ) -> bool {
+        let (ox, oy) = obstruction;
+        let mut guard = from;
+
+        loop {
+            let state = self.cell(guard.x, guard.y) * 4 + guard.direction as usize;
+            if visited[state] == stamp {
+                return true;
+            }
+            visited[state] = stamp;
+
+            let (dx, dy) = guard.direction.advance();
+            let stop = self.jumps[state].map(|cell| {
+                let (sx, sy) = ((cell / self.width) as i32, (cell % self.width) as i32);
+                (sx - guard.x) * dx + (sy - guard.y) * dy
+            });
+
+            let in_line = match dx {
+                0 => ox == guard.x,
+                _ => oy == guard.y,
+            };
+            let distance = (ox - guard.x) * dx + (oy - guard.y) * dy;
+            let stop = match in_line && distance > 0 && stop.is_none_or(|stop| distance <= stop) {
+                true => Some(distance - 1),
+                false => stop,
+            };
+
+            let Some(stop) = stop else {
+                return false;
+            };
+
+            guard = Guard {
+                x: guard.x + dx * stop,
+                y: guard.y + dy * stop,
+                direction: guard.direction.turn(),
+            };
+        }
     }
 
-    TraverseResult::Terminated(visited_pos)
+    // each cell on the original path paired with the guard state just
+    // before it first walks onto it, i.e. where a new obstruction would be hit
+    fn candidates(&self) -> Vec<((i32, i32), Guard)> {
+        let path = self.walk(None).steps;
+        let mut seen = vec![false; self.width * self.height];
+        seen[self.cell(self.guard.x, self.guard.y)] = true;
+
+        path.windows(2)
+            .filter_map(|pair| {
+                let cell = self.cell(pair[1].x, pair[1].y);
+                match seen[cell] {
+                    true => None,
+                    false => {
+                        seen[cell] = true;
+                        Some(((pair[1].x, pair[1].y), pair[0]))
+                    }
+                }
+            })
+            .collect()
+    }
 }
 
 pub fn part1(data: &str) -> u32 {
     let map = parse(data);
+    let trajectory = Lab::new(&map).walk(None);
 
-    let result: u32 = match traverse(&map) {
-        TraverseResult::Terminated(hash_set) => hash_set.len() as u32,
-        TraverseResult::Cycle => panic!("Failed to complete!"),
-    };
+    if trajectory.looped {
+        panic!("Failed to complete!");
+    }
+
+    let visited: HashSet<(i32, i32)> = trajectory
+        .steps
+        .iter()
+        .map(|guard| (guard.x, guard.y))
+        .collect();
 
-    result
+    visited.len() as u32
 }
 
-pub fn part2(data: &str) -> u32 {
-    // basically brute force with a small improvement - we only check the initial
-    // visited path...
-    //
-    let mut map = parse(data);
-    let guard = get_guard(&map).unwrap();
-
-    let visited: HashSet<(i32, i32)> = match traverse(&map) {
-        TraverseResult::Terminated(hash_set) => hash_set,
-        TraverseResult::Cycle => panic!("Failed to complete!"),
-    };
+pub fn patrol(data: &str, obstruction: Option<(i32, i32)>) -> Trajectory {
+    let map = parse(data);
 
-    let mut result: u32 = 0;
-    let mut attempt: u32 = 0;
-    for (a, b) in visited {
-        attempt += 1;
-        if !(guard.x == a && guard.y == b) {
-            let tmp: char = map[a as usize][b as usize];
-            map[a as usize][b as usize] = '#';
-
-            match traverse(&map) {
-                TraverseResult::Cycle => result += 1,
-                TraverseResult::Terminated(_) => {}
-            }
+    Lab::new(&map).walk(obstruction)
+}
+
+// candidates are split into one chunk per worker, each with its own visited
+// bitmap since the obstruction is only ever an overlay on the shared lab.
+// obstructions come back in the order the guard first reaches them
+pub fn loop_obstructions(data: &str, threads: usize) -> Vec<(i32, i32)> {
+    let map = parse(data);
+    let lab = Lab::new(&map);
+    let candidates = lab.candidates();
+    let chunk_size = candidates.len().div_ceil(threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let lab = &lab;
+                scope.spawn(move || {
+                    let mut visited = vec![0; lab.width * lab.height * 4];
+
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, (obstruction, from))| {
+                            lab.loops_with(*obstruction, *from, &mut visited, *i as u32 + 1)
+                        })
+                        .map(|(_, (obstruction, _))| *obstruction)
+                        .collect::<Vec<(i32, i32)>>()
+                })
+            })
+            .collect();
 
-            map[a as usize][b as usize] = tmp;
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Worker panicked"))
+            .collect()
+    })
+}
+
+pub fn count_loops(data: &str, threads: usize) -> u32 {
+    loop_obstructions(data, threads).len() as u32
+}
+
+// draws the trajectory the way the puzzle text does: | and - for the
+// direction of travel, + where the guard turns or crosses its own path,
+// and O for each of the given obstructions
+pub fn render(data: &str, trajectory: &Trajectory, obstructions: &[(i32, i32)]) -> String {
+    let mut map: Vec<Vec<char>> = parse(data)
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&ch| match ch {
+                    '#' => '#',
+                    _ => '.',
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut mark = |x: i32, y: i32, ch: char| {
+        let cell = &mut map[x as usize][y as usize];
+        *cell = match *cell {
+            '.' => ch,
+            current if current == ch => ch,
+            _ => '+',
+        };
+    };
+
+    for pair in trajectory.steps.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        if (from.x, from.y) == (to.x, to.y) {
+            mark(from.x, from.y, '+');
+            continue;
         }
-        println!("#{attempt}");
+
+        let ch = match from.direction {
+            Direction::North | Direction::South => '|',
+            Direction::East | Direction::West => '-',
+        };
+        mark(from.x, from.y, ch);
+        mark(to.x, to.y, ch);
     }
 
-    result
+    for &(x, y) in obstructions {
+        map[x as usize][y as usize] = 'O';
+    }
+
+    if let Some(start) = trajectory.steps.first() {
+        map[start.x as usize][start.y as usize] = match start.direction {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        };
+    }
+
+    map.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn part2(data: &str) -> u32 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    count_loops(data, threads)
 }
 
 #[cfg(test)]
@@ -172,4 +379,57 @@ mod tests {
 ......#...";
         assert_eq!(part2(data), 6);
     }
+
+    #[test]
+    fn count_loops_works() {
+        let data = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+        for threads in 1..=8 {
+            assert_eq!(count_loops(data, threads), 6);
+        }
+    }
+
+    #[test]
+    fn loop_obstructions_works() {
+        let data = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+        let mut obstructions = loop_obstructions(data, 3);
+        obstructions.sort();
+        assert_eq!(
+            obstructions,
+            vec![(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]
+        );
+
+        let trajectory = patrol(data, Some((6, 3)));
+        assert!(trajectory.looped);
+        assert_eq!(
+            render(data, &trajectory, &[(6, 3)]),
+            "....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#..."
+        );
+    }
 }
@@ -1,172 +1,327 @@
-use std::collections::VecDeque;
-
-use aoc2024::triangular;
+use std::cmp::{min, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
 
 #[derive(Copy, Clone, Debug)]
 enum Block {
-    File(u8, u64),
-    Free(u8),
+    File(u64, u64),
+    Free(u64),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    Fragment,
+    WholeFile,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Extent {
+    pub id: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disk {
+    pub extents: Vec<Extent>,
+    pub size: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fragmentation {
+    pub free_gaps: usize,
+    pub largest_gap: u64,
+    pub files_split: usize,
 }
 
+// the puzzle input is one digit per size, the extended encoding separates
+// sizes with commas so they can go past 9. A single entry in the extended
+// encoding needs a trailing comma (`12,`) or it reads as two digits
 fn parse(data: &str) -> VecDeque<Block> {
+    let data = data.trim();
+    let sizes: Vec<u64> = match data.contains(',') {
+        true => data
+            .strip_suffix(',')
+            .unwrap_or(data)
+            .split(',')
+            .map(|size| size.trim().parse().expect("Invalid size"))
+            .collect(),
+        false => data
+            .bytes()
+            .map(|byte| match byte.is_ascii_digit() {
+                true => (byte - b'0') as u64,
+                false => panic!("Invalid size"),
+            })
+            .collect(),
+    };
+
     let mut dequeue = VecDeque::new();
-    data.trim()
-        .chars()
-        .map(|ch| ch.to_string().parse().unwrap())
-        .enumerate()
-        .for_each(|(i, digit)| {
-            if digit == 0 {
-            } else if i % 2 == 0 {
-                dequeue.push_back(Block::File(digit, i as u64 / 2));
-            } else {
-                dequeue.push_back(Block::Free(digit));
-            }
-        });
+    sizes.into_iter().enumerate().for_each(|(i, size)| {
+        if size == 0 {
+        } else if i % 2 == 0 {
+            dequeue.push_back(Block::File(size, i as u64 / 2));
+        } else {
+            dequeue.push_back(Block::Free(size));
+        }
+    });
 
     dequeue
 }
 
-fn checksum(filesystem: &VecDeque<Block>) -> u64 {
-    let mut result: u64 = 0;
+// spans this long or longer share one bucket, shorter ones get a bucket per
+// size. Puzzle input sizes are single digits so they never reach it
+const LARGE_SPAN: u64 = 10;
 
-    let mut idx: u64 = 0;
-    for block in filesystem {
-        match block {
-            Block::Free(size) => {
-                idx += *size as u64;
+// free spans bucketed by their size, each small bucket a min-heap on offset so
+// the leftmost span of a given size is always on top. Large spans are kept in
+// offset order with their sizes, so a request is at most LARGE_SPAN heap peeks
+// plus a scan of the large spans before `before` when it's itself large
+struct FreeSpans {
+    small: Vec<BinaryHeap<Reverse<u64>>>,
+    large: BTreeMap<u64, u64>,
+}
+
+impl FreeSpans {
+    fn new() -> FreeSpans {
+        FreeSpans {
+            small: vec![BinaryHeap::new(); LARGE_SPAN as usize],
+            large: BTreeMap::new(),
+        }
+    }
+
+    fn push(&mut self, offset: u64, size: u64) {
+        match size {
+            0 => {}
+            size if size < LARGE_SPAN => self.small[size as usize].push(Reverse(offset)),
+            size => {
+                self.large.insert(offset, size);
+            }
+        }
+    }
+
+    // removes the leftmost span that can hold `size` blocks, as long as it
+    // starts before `before`
+    fn take(&mut self, size: u64, before: u64) -> Option<(u64, u64)> {
+        let small = (size.min(LARGE_SPAN) as usize..LARGE_SPAN as usize)
+            .filter_map(|span| {
+                self.small[span]
+                    .peek()
+                    .map(|Reverse(offset)| (*offset, span as u64))
+            })
+            .min();
+        let large = self
+            .large
+            .range(..before)
+            .find(|(_, &span)| span >= size)
+            .map(|(&offset, &span)| (offset, span));
+
+        let (offset, span) = small.into_iter().chain(large).min()?;
+        if offset >= before {
+            return None;
+        }
+
+        match span < LARGE_SPAN {
+            true => {
+                self.small[span as usize].pop();
             }
-            Block::File(size, id) => {
-                result += triangular(idx, idx + *size as u64 - 1) * id;
-                idx += *size as u64;
+            false => {
+                self.large.remove(&offset);
             }
         }
+
+        Some((offset, span))
     }
-    result
 }
 
-pub fn part1(data: &str) -> u64 {
-    let mut filesystem = parse(data);
+fn compact_blocks(filesystem: &VecDeque<Block>, strategy: Strategy) -> Disk {
+    let mut files: Vec<Extent> = Vec::new();
+    let mut free = FreeSpans::new();
+
+    let mut offset: u64 = 0;
+    for block in filesystem {
+        match *block {
+            Block::File(size, id) => files.push(Extent { id, offset, size }),
+            Block::Free(size) => free.push(offset, size),
+        }
+        offset += match *block {
+            Block::File(size, _) | Block::Free(size) => size,
+        };
+    }
 
-    let mut i: usize = 0;
+    let mut result: Vec<Extent> = Vec::new();
+    for file in files.iter().rev() {
+        let mut remaining = file.size;
 
-    while i < filesystem.len() - 1 {
-        let block = filesystem.remove(i).unwrap();
-        match block {
-            Block::File(_, _) => {
-                // just put it back...
-                filesystem.insert(i, block);
-                i += 1;
+        match strategy {
+            Strategy::Fragment => {
+                while remaining > 0 {
+                    let Some((offset, span)) = free.take(1, file.offset) else {
+                        break;
+                    };
+                    let size = min(span, remaining);
+
+                    result.push(Extent {
+                        id: file.id,
+                        offset,
+                        size,
+                    });
+                    free.push(offset + size, span - size);
+                    remaining -= size;
+                }
             }
-            Block::Free(free_size) => {
-                let end_block = filesystem.pop_back().unwrap();
-
-                match end_block {
-                    Block::Free(_) => {
-                        // put back the block we took,
-                        // ignore the free block we popped - leave it popped...
-                        filesystem.insert(i, block);
-                        // continue on and try again (don't increment i).
-                    }
-                    Block::File(end_size, id) => {
-                        if end_size <= free_size {
-                            // then use up some of that free space
-                            filesystem.insert(i, Block::File(end_size, id));
-                        }
-
-                        if end_size < free_size {
-                            // if there's space left over, then it's free space
-                            filesystem.insert(i + 1, Block::Free(free_size - end_size));
-                        }
-
-                        if end_size > free_size {
-                            // if we can't fit it, just fit as much as we can,
-                            // and put the rest at the nd
-                            filesystem.insert(i, Block::File(free_size, id));
-                            filesystem.push_back(Block::File(end_size - free_size, id));
-                        }
-                        i += 1;
-                    }
+            Strategy::WholeFile => {
+                if let Some((offset, span)) = free.take(file.size, file.offset) {
+                    result.push(Extent { offset, ..*file });
+                    free.push(offset + file.size, span - file.size);
+                    remaining = 0;
                 }
             }
         }
+
+        // whatever couldn't be moved stays at the front of where it was
+        if remaining > 0 {
+            result.push(Extent {
+                size: remaining,
+                ..*file
+            });
+        }
+    }
+
+    result.sort_by_key(|extent| extent.offset);
+
+    // a moved piece can land right up against what's left of its own file
+    let mut extents: Vec<Extent> = Vec::new();
+    for extent in result {
+        match extents.last_mut() {
+            Some(last) if last.id == extent.id && last.offset + last.size == extent.offset => {
+                last.size += extent.size;
+            }
+            _ => extents.push(extent),
+        }
     }
 
-    checksum(&filesystem)
+    Disk {
+        extents,
+        size: offset,
+    }
 }
 
-pub fn part2(data: &str) -> u64 {
-    // it's pretty clear at this point that i need to study some rust, as
-    // I don't know what I'm doing.
-    // I'm copying everything when I don't understand how to do something, as a
-    // result this is horrendously slow.
-
-    let mut filesystem = parse(data);
-
-    let last_file_id: u64 = *filesystem
-        .iter()
-        .filter_map(|block| match block {
-            Block::File(_, id) => Some(id),
-            Block::Free(_) => None,
-        })
-        .last()
-        .unwrap();
-
-    for disk_id in (0..=last_file_id).rev() {
-        let pos: usize = filesystem
+impl Disk {
+    pub fn checksum(&self) -> u128 {
+        self.extents
             .iter()
-            .enumerate()
-            .filter(|(_, &block)| match block {
-                Block::File(_, id) => id == disk_id,
-                _ => false,
+            .map(|extent| {
+                let (offset, size) = (extent.offset as u128, extent.size as u128);
+                size * (2 * offset + size - 1) / 2 * extent.id as u128
             })
-            .next()
-            .unwrap()
-            .0;
-
-        let block = filesystem.remove(pos).unwrap();
-        let mut moved: bool = false;
-        match block {
-            Block::File(size, _) => {
-                for j in 0..pos {
-                    let free_block = filesystem.remove(j).unwrap();
-                    match free_block {
-                        Block::File(_, _) => {
-                            filesystem.insert(j, free_block);
-                        }
-                        Block::Free(free_size) => {
-                            if size < free_size {
-                                filesystem.insert(j, block);
-                                filesystem.insert(pos, Block::Free(size));
-                                filesystem.insert(j + 1, Block::Free(free_size - size));
-                                moved = true;
-                                break;
-                            }
-
-                            if size == free_size {
-                                filesystem.insert(j, block);
-                                filesystem.insert(pos, Block::Free(size));
-                                moved = true;
-                                break;
-                            }
-
-                            if size > free_size {
-                                filesystem.insert(j, free_block);
-                            }
-                        }
-                    }
-                }
+            .sum()
+    }
 
-                if !moved {
-                    filesystem.insert(pos, block);
-                }
+    fn sizes(&self) -> Vec<u64> {
+        let mut sizes: Vec<u64> = Vec::new();
+        let mut cursor: u64 = 0;
+
+        for extent in self.extents.iter() {
+            let gap = extent.offset - cursor;
+            if !sizes.is_empty() {
+                sizes.push(gap);
+            } else if gap > 0 {
+                sizes.extend([0, gap]);
             }
-            Block::Free(_) => {
-                panic!("Should never happen");
+
+            sizes.push(extent.size);
+            cursor = extent.offset + extent.size;
+        }
+
+        if self.size > cursor {
+            sizes.push(self.size - cursor);
+        }
+
+        sizes
+    }
+
+    // sizes alternating file, free, file... as in the puzzle input. ids are
+    // implied by position in that format so only the layout survives, and
+    // anything over 9 blocks is split with a zero sized entry in between
+    pub fn serialise(&self) -> String {
+        let mut result = String::new();
+        for size in self.sizes() {
+            let mut size = size;
+            while size > 9 {
+                result.push_str("90");
+                size -= 9;
             }
+            result.push(char::from_digit(size as u32, 10).unwrap());
         }
+
+        result
     }
 
-    checksum(&filesystem)
+    // same layout in the comma separated encoding, nothing needs splitting.
+    // A lone entry keeps a trailing comma so it reads back the same way
+    pub fn serialise_extended(&self) -> String {
+        let sizes = self.sizes();
+        let result = sizes
+            .iter()
+            .map(|size| size.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        match sizes.len() {
+            1 => result + ",",
+            _ => result,
+        }
+    }
+
+    // the expanded block view from the puzzle text, ids past 9 only show
+    // their last digit
+    pub fn render(&self) -> String {
+        let mut blocks = vec!['.'; self.size as usize];
+
+        for extent in self.extents.iter() {
+            let ch = char::from_digit((extent.id % 10) as u32, 10).unwrap();
+            for block in extent.offset..extent.offset + extent.size {
+                blocks[block as usize] = ch;
+            }
+        }
+
+        blocks.iter().collect()
+    }
+
+    // gaps only count free space between files, not the run at the end
+    pub fn fragmentation(&self) -> Fragmentation {
+        let gaps: Vec<u64> = self
+            .extents
+            .windows(2)
+            .map(|pair| pair[1].offset - (pair[0].offset + pair[0].size))
+            .filter(|&gap| gap > 0)
+            .collect();
+
+        let mut extents_per_file: HashMap<u64, usize> = HashMap::new();
+        for extent in self.extents.iter() {
+            *extents_per_file.entry(extent.id).or_default() += 1;
+        }
+
+        Fragmentation {
+            free_gaps: gaps.len(),
+            largest_gap: gaps.iter().copied().max().unwrap_or(0),
+            files_split: extents_per_file
+                .values()
+                .filter(|&&count| count > 1)
+                .count(),
+        }
+    }
+}
+
+pub fn compact(data: &str, strategy: Strategy) -> Disk {
+    compact_blocks(&parse(data), strategy)
+}
+
+pub fn part1(data: &str) -> u128 {
+    compact(data, Strategy::Fragment).checksum()
+}
+
+pub fn part2(data: &str) -> u128 {
+    compact(data, Strategy::WholeFile).checksum()
 }
 
 #[cfg(test)]
@@ -184,4 +339,51 @@ mod tests {
         let data = "2333133121414131402";
         assert_eq!(part2(data), 2858);
     }
+
+    #[test]
+    fn disk_layout_works() {
+        let data = "2333133121414131402";
+
+        let disk = compact(data, Strategy::Fragment);
+        assert_eq!(disk.render(), "0099811188827773336446555566..............");
+        assert_eq!(disk.serialise(), "2020103030103030102010402905");
+        assert_eq!(
+            disk.fragmentation(),
+            Fragmentation {
+                free_gaps: 0,
+                largest_gap: 0,
+                files_split: 2,
+            }
+        );
+
+        let disk = compact(data, Strategy::WholeFile);
+        assert_eq!(disk.render(), "00992111777.44.333....5555.6666.....8888..");
+        assert_eq!(disk.serialise(), "20201030312134414542");
+        assert_eq!(disk.fragmentation().free_gaps, 5);
+        assert_eq!(disk.fragmentation().largest_gap, 5);
+    }
+
+    #[test]
+    fn extended_encoding_works() {
+        assert_eq!(part1("2,3,3,3,1,3,3,1,2,1,4,1,4,1,3,1,4,0,2"), 1928);
+        assert_eq!(part2("2,3,3,3,1,3,3,1,2,1,4,1,4,1,3,1,4,0,2"), 2858);
+
+        let disk = compact("12,3,1,20", Strategy::WholeFile);
+        assert_eq!(disk.serialise_extended(), "12,0,1,23");
+        assert_eq!(disk.serialise(), "9030190905");
+        assert_eq!(disk.checksum(), 12);
+
+        let disk = compact("12,", Strategy::WholeFile);
+        assert_eq!(
+            (disk.size, disk.serialise_extended()),
+            (12, "12,".to_string())
+        );
+        assert_eq!(compact("12", Strategy::WholeFile).size, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid size")]
+    fn invalid_size_panics() {
+        part1("23x3");
+    }
 }